@@ -0,0 +1,240 @@
+
+use lib_game::GResult;
+use lib_game::sprite::SpriteSheet;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::fs;
+
+
+/// what a clip do once its last frame have been shown
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayMode {
+    /// start again from the first frame
+    Loop,
+    /// stay on the last frame and report that the clip is finished
+    Once
+}
+
+#[derive(Debug,Clone,Deserialize)]
+struct ClipData {
+    name:           String,
+    /// number of frame, placed one after the other in the spritesheet
+    frames:         u32,
+    x:              f32,
+    y:              f32,
+    /// space between two frame in the spritesheet
+    #[serde(default)]
+    spacing:        f32,
+    /// number of game frame each frame of the clip stay on screen
+    frame_duration: u32,
+    mode:           PlayMode
+}
+
+/// content of the animation file that sit next to each sprite png
+#[derive(Debug,Clone,Deserialize)]
+struct AnimationData {
+    frame_width:    f32,
+    frame_height:   f32,
+    /// the first clip is the one played by default
+    clips:          Vec<ClipData>
+}
+
+
+/// position of the player in its clips, enough to show the same frame again later
+#[derive(Debug,Clone,PartialEq,Deserialize,Serialize)]
+pub struct AnimationState {
+    pub clip:       String,
+    pub frame:      u32,
+    pub timer:      u32,
+    pub finished:   bool
+}
+
+
+struct Clip {
+    frames:         u32,
+    frame_duration: u32,
+    mode:           PlayMode
+}
+
+
+/// play the named clips of a spritesheet at their own speed
+pub struct AnimationPlayer {
+    spritesheet:    SpriteSheet,
+    clips:          HashMap<String,Clip>,
+    current:        String,
+    frame:          u32,
+    timer:          u32,
+    finished:       bool
+}
+
+impl AnimationPlayer {
+
+    /// create the spritesheet and its clips from an animation file
+    pub fn load(path:&str) -> GResult<Self> {
+
+        let content = fs::read_to_string(path)?;
+
+        let data: AnimationData = match serde_json::from_str(&content) {
+            Ok(data) => data,
+            Err(e) => bail!("invalid animation file '{}': {}",path,e)
+        };
+
+        let current = match data.clips.first() {
+            Some(clip) => clip.name.clone(),
+            None => bail!("animation file '{}' has no clip",path)
+        };
+
+        let mut spritesheet = SpriteSheet::new(data.frame_width,data.frame_height);
+        let mut clips = HashMap::new();
+
+        for clip in data.clips.into_iter() {
+
+            if clip.frames == 0 || clip.frame_duration == 0 {
+                bail!("clip '{}' of '{}' need at least one frame of one tick",clip.name,path);
+            }
+
+            spritesheet.add_sprite(&clip.name,clip.frames as _,clip.x,clip.y,clip.spacing)?;
+
+            clips.insert(clip.name, Clip {
+                frames: clip.frames,
+                frame_duration: clip.frame_duration,
+                mode: clip.mode
+            });
+
+        }
+
+        Ok(Self {
+            spritesheet,
+            clips,
+            current,
+            frame: 0,
+            timer: 0,
+            finished: false
+        })
+
+    }
+
+    pub fn get_spritesheet(&self) -> &SpriteSheet { &self.spritesheet }
+    pub fn get_spritesheet_mut(&mut self) -> &mut SpriteSheet { &mut self.spritesheet }
+    pub fn get_clip(&self) -> &str { &self.current }
+
+    /// start a clip from its first frame, nothing change if it is already playing
+    pub fn play(&mut self,name:&str) -> GResult<()> {
+
+        if self.current == name {
+            return Ok(());
+        }
+
+        if !self.clips.contains_key(name) {
+            bail!("no clip named '{}'",name);
+        }
+
+        self.spritesheet.change_current(name)?;
+
+        self.current = name.to_string();
+        self.frame = 0;
+        self.timer = 0;
+        self.finished = false;
+
+        Ok(())
+
+    }
+
+    /// advance the clip by one game frame, return true when a one-shot clip just ended
+    pub fn tick(&mut self) -> GResult<bool> {
+
+        if self.finished {
+            return Ok(false);
+        }
+
+        let clip = &self.clips[&self.current];
+
+        self.timer += 1;
+
+        if self.timer < clip.frame_duration {
+            return Ok(false);
+        }
+
+        self.timer = 0;
+
+        if self.frame + 1 < clip.frames {
+
+            self.spritesheet.increment_current_sprite()?;
+            self.frame += 1;
+
+        } else {
+
+            match clip.mode {
+                PlayMode::Loop => {
+                    // the spritesheet go back to the first frame by itself
+                    if clip.frames > 1 {
+                        self.spritesheet.increment_current_sprite()?;
+                    }
+                    self.frame = 0;
+                },
+                PlayMode::Once => {
+                    self.finished = true;
+                    return Ok(true);
+                }
+            }
+
+        }
+
+        Ok(false)
+
+    }
+
+    pub fn get_state(&self) -> AnimationState {
+        AnimationState {
+            clip: self.current.clone(),
+            frame: self.frame,
+            timer: self.timer,
+            finished: self.finished
+        }
+    }
+
+    /// fail when the state isn't a frame of one of the clips, return the number of frame of its clip
+    pub fn check_state(&self,state:&AnimationState) -> GResult<u32> {
+
+        let frames = match self.clips.get(&state.clip) {
+            Some(clip) => clip.frames,
+            None => bail!("no clip named '{}'",state.clip)
+        };
+
+        if state.frame >= frames {
+            bail!("the clip '{}' has no frame {}",state.clip,state.frame);
+        }
+
+        Ok(frames)
+
+    }
+
+    /// go back to the frame of a state, the spritesheet can only move forward so it loop to it
+    pub fn set_state(&mut self,state:&AnimationState) -> GResult<()> {
+
+        let frames = self.check_state(state)?;
+
+        let mut shown = self.frame;
+
+        if self.current != state.clip {
+            self.spritesheet.change_current(&state.clip)?;
+            self.current = state.clip.clone();
+            shown = 0;
+        }
+
+        for _ in 0..(state.frame + frames - shown) % frames {
+            self.spritesheet.increment_current_sprite()?;
+        }
+
+        self.frame = state.frame;
+        self.timer = state.timer;
+        self.finished = state.finished;
+
+        Ok(())
+
+    }
+
+}
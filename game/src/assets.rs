@@ -0,0 +1,82 @@
+
+use lib_game::GResult;
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+
+/// environment variable that give the assets directory
+pub const ASSETS_ENV: &str = "RUSTY_MARIO_ASSETS";
+
+/// name of the assets directory next to the executable or in the working directory
+const ASSETS_NAME: &str = "assets";
+
+static ASSETS_DIR: OnceLock<String> = OnceLock::new();
+
+
+/// an assets directory always have the tileset in it
+fn is_assets_dir(path:&Path) -> bool {
+    path.join("tiles").is_dir()
+}
+
+/// every directory that may hold the assets, from the most to the less important
+fn get_candidates(flag:Option<&str>) -> Vec<(String,PathBuf)> {
+
+    let mut candidates = Vec::new();
+
+    if let Some(flag) = flag {
+        candidates.push(("--assets".to_string(),PathBuf::from(flag)));
+    }
+
+    if let Ok(dir) = env::var(ASSETS_ENV) {
+        candidates.push((ASSETS_ENV.to_string(),PathBuf::from(dir)));
+    }
+
+    if let Some(dir) = env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+        candidates.push(("executable directory".to_string(),dir.join(ASSETS_NAME)));
+    }
+
+    // launched from the game directory of the repository
+    candidates.push(("working directory".to_string(),PathBuf::from(ASSETS_NAME)));
+    candidates.push(("working directory".to_string(),Path::new("..").join(ASSETS_NAME)));
+
+    candidates
+
+}
+
+/// find the assets directory, the flag come first, then the environment variable,
+/// the directory of the executable and the working directory
+pub fn init(flag:Option<&str>) -> GResult<()> {
+
+    let candidates = get_candidates(flag);
+
+    let dir = match candidates.iter().find(|(_,path)| is_assets_dir(path)) {
+        Some((_,path)) => path,
+        None => {
+
+            let tried: Vec<String> = candidates.iter()
+                .map(|(from,path)| format!("  {} ({})",path.display(),from))
+                .collect();
+
+            bail!("can't find the assets directory, tried:\n{}\nuse --assets <dir> or set {}",tried.join("\n"),ASSETS_ENV)
+
+        }
+    };
+
+    if ASSETS_DIR.set(dir.to_string_lossy().into_owned()).is_err() {
+        bail!("the assets directory is already set");
+    }
+
+    Ok(())
+
+}
+
+/// directory found by init
+pub fn get_assets_dir() -> &'static str {
+    ASSETS_DIR.get().map_or(ASSETS_NAME,String::as_str)
+}
+
+pub fn get_tile_dir() -> String {
+    format!("{}/tiles",get_assets_dir())
+}
@@ -0,0 +1,152 @@
+
+use crate::map::{Palette, TileKind};
+
+use macroquad::prelude::{
+    Color,
+    FilterMode,
+    Image,
+    Texture2D,
+    Rect as r
+};
+
+
+/// width of the atlas texture, it grow in height
+const ATLAS_WIDTH: usize = 256;
+
+/// transparent pixel around each tile so a tile never show a bit of its neighbour
+const PADDING: usize = 1;
+
+
+/// image of one tile of the tileset
+struct TileImage {
+    id:     i32,
+    image:  Image,
+    wall:   bool
+}
+
+
+/// every tile image of a level, packed into one texture when the map is loaded
+#[derive(Default)]
+pub struct AtlasBuilder {
+    tiles:  Vec<TileImage>
+}
+
+impl AtlasBuilder {
+
+    pub fn new() -> Self { Self::default() }
+
+    /// add the part of the image given by source, the whole image when none
+    pub fn add(&mut self,id:i32,image:&Image,source:Option<r>,wall:bool) {
+
+        let image = match source {
+            Some(source) => image.sub_image(source),
+            None => image.clone()
+        };
+
+        self.tiles.push(TileImage { id, image, wall });
+
+    }
+
+    /// pack every tile into one texture, the tiles of the palette are parts of it
+    pub fn build(&self) -> Palette {
+
+        let mut palette = Palette::new();
+
+        if self.tiles.is_empty() {
+            return palette;
+        }
+
+        // the tallest tiles first so each row waste less space
+        let mut order: Vec<&TileImage> = self.tiles.iter().collect();
+        order.sort_by_key(|tile| std::cmp::Reverse(tile.image.height));
+
+        let width = order.iter()
+            .map(|tile| tile.image.width as usize + PADDING * 2)
+            .max()
+            .unwrap_or(0)
+            .max(ATLAS_WIDTH);
+
+        // place the tiles row by row
+        let mut places = Vec::with_capacity(order.len());
+        let (mut x,mut y,mut row_height) = (0,0,0);
+
+        for tile in order.iter() {
+
+            let w = tile.image.width as usize + PADDING * 2;
+            let h = tile.image.height as usize + PADDING * 2;
+
+            if x + w > width {
+                x = 0;
+                y += row_height;
+                row_height = 0;
+            }
+
+            places.push((x + PADDING,y + PADDING));
+
+            x += w;
+            row_height = row_height.max(h);
+
+        }
+
+        let height = y + row_height;
+
+        let mut atlas = Image::gen_image_color(width as u16,height as u16,Color::new(0.0,0.0,0.0,0.0));
+
+        for (tile,(x,y)) in order.iter().zip(places.iter()) {
+            copy_image(&tile.image,&mut atlas,*x,*y);
+        }
+
+        let texture = Texture2D::from_image(&atlas);
+        texture.set_filter(FilterMode::Nearest);
+
+        for (tile,(x,y)) in order.iter().zip(places.iter()) {
+            palette.insert(tile.id,TileKind {
+                texture,
+                source: Some(r::new(*x as f32,*y as f32,tile.image.width as f32,tile.image.height as f32)),
+                wall: tile.wall
+            });
+        }
+
+        palette
+
+    }
+
+    /// one texture by tile, like before the atlas, only used to compare the two
+    pub fn build_separate(&self) -> Palette {
+
+        self.tiles.iter()
+            .map(|tile| {
+
+                let texture = Texture2D::from_image(&tile.image);
+                texture.set_filter(FilterMode::Nearest);
+
+                (tile.id,TileKind {
+                    texture,
+                    source: None,
+                    wall: tile.wall
+                })
+
+            })
+            .collect()
+
+    }
+
+}
+
+
+/// copy every pixel of the image into the atlas with its top left corner at x y
+fn copy_image(image:&Image,atlas:&mut Image,x:usize,y:usize) {
+
+    let line = image.width as usize * 4;
+    let atlas_line = atlas.width as usize * 4;
+
+    for row in 0..image.height as usize {
+
+        let from = row * line;
+        let to = (y + row) * atlas_line + x * 4;
+
+        atlas.bytes[to..to + line].copy_from_slice(&image.bytes[from..from + line]);
+
+    }
+
+}
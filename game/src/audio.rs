@@ -0,0 +1,283 @@
+
+use crate::assets::get_assets_dir;
+
+use macroquad::audio::{
+    Sound,
+    load_sound,
+    play_sound,
+    stop_sound,
+    set_sound_volume,
+    PlaySoundParams
+};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+
+/// amount of volume added or removed by one press of the volume key
+const VOLUME_STEP: f32 = 0.1;
+
+
+/// every short sound the gameplay can ask for
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum SoundEffect {
+    Jump,
+    Stomp,
+    Bump,
+    Coin,
+    Death,
+    LevelClear,
+    OneUp,
+    PowerUp,
+    Fireball
+}
+
+impl SoundEffect {
+
+    const ALL: [SoundEffect; 9] = [
+        SoundEffect::Jump,
+        SoundEffect::Stomp,
+        SoundEffect::Bump,
+        SoundEffect::Coin,
+        SoundEffect::Death,
+        SoundEffect::LevelClear,
+        SoundEffect::OneUp,
+        SoundEffect::PowerUp,
+        SoundEffect::Fireball
+    ];
+
+    fn get_name(&self) -> &'static str {
+        match self {
+            SoundEffect::Jump => "jump",
+            SoundEffect::Stomp => "stomp",
+            SoundEffect::Bump => "bump",
+            SoundEffect::Coin => "coin",
+            SoundEffect::Death => "death",
+            SoundEffect::LevelClear => "level-clear",
+            SoundEffect::OneUp => "one-up",
+            SoundEffect::PowerUp => "power-up",
+            SoundEffect::Fireball => "fireball"
+        }
+    }
+
+}
+
+
+/// loudness of the game, every volume is between 0 and 1
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub volume:         f32,
+    pub music_volume:   f32,
+    pub effect_volume:  f32,
+    pub muted:          bool
+}
+
+impl Default for AudioSettings {
+
+    fn default() -> Self {
+        Self {
+            volume: 0.8,
+            music_volume: 0.6,
+            effect_volume: 1.0,
+            muted: false
+        }
+    }
+
+}
+
+impl AudioSettings {
+
+    fn get_music_volume(&self) -> f32 {
+        if self.muted { 0.0 } else { self.volume * self.music_volume }
+    }
+
+    fn get_effect_volume(&self) -> f32 {
+        if self.muted { 0.0 } else { self.volume * self.effect_volume }
+    }
+
+}
+
+
+/// music of the level, the hurry version replace the normal one when the time run low
+#[derive(Debug,Clone,Copy,PartialEq)]
+enum Track {
+    Normal,
+    Hurry,
+    /// played while the player has the star power
+    Star
+}
+
+
+/// load and play the music of the level and the sound effects
+pub struct AudioManager {
+    effects:        HashMap<SoundEffect,Sound>,
+    music:          Option<Sound>,
+    hurry_music:    Option<Sound>,
+    /// the same for every level
+    star_music:     Option<Sound>,
+    playing:        Option<Track>,
+    settings:       AudioSettings
+}
+
+impl AudioManager {
+
+    /// load every sound of the level, a missing file only make the game quieter
+    pub async fn init(level:&str) -> Self {
+
+        let mut effects = HashMap::new();
+
+        for effect in SoundEffect::ALL {
+            if let Some(sound) = try_load_sound(&format!("{}/sounds/{}.wav",get_assets_dir(),effect.get_name())).await {
+                effects.insert(effect,sound);
+            }
+        }
+
+        let mut audio = Self {
+            effects,
+            music: None,
+            hurry_music: None,
+            star_music: try_load_sound(&format!("{}/music/star.wav",get_assets_dir())).await,
+            playing: None,
+            settings: AudioSettings::default()
+        };
+
+        audio.load_music(level).await;
+
+        audio
+
+    }
+
+    /// replace the music by the one of another level, the settings stay the same
+    pub async fn load_music(&mut self,level:&str) {
+
+        self.stop_music();
+
+        self.music = try_load_sound(&format!("{}/music/{}.wav",get_assets_dir(),level)).await;
+        self.hurry_music = try_load_sound(&format!("{}/music/{}-hurry.wav",get_assets_dir(),level)).await;
+
+    }
+
+    pub fn play(&self,effect:SoundEffect) {
+
+        if self.settings.muted {
+            return;
+        }
+
+        if let Some(sound) = self.effects.get(&effect) {
+            play_sound(*sound,PlaySoundParams {
+                looped: false,
+                volume: self.settings.get_effect_volume()
+            });
+        }
+
+    }
+
+    /// start the normal music of the level from its beginning
+    pub fn start_music(&mut self) {
+        self.play_track(Track::Normal);
+    }
+
+    /// switch to the faster music, nothing change if it is already playing
+    pub fn hurry_up(&mut self) {
+        if self.playing != Some(Track::Hurry) {
+            self.play_track(Track::Hurry);
+        }
+    }
+
+    /// switch to the star music from its beginning
+    pub fn star_power(&mut self) {
+        self.play_track(Track::Star);
+    }
+
+    pub fn stop_music(&mut self) {
+
+        if let Some(sound) = self.playing.and_then(|track| self.get_track(track)) {
+            stop_sound(sound);
+        }
+
+        self.playing = None;
+
+    }
+
+    pub fn get_settings(&self) -> AudioSettings { self.settings }
+
+    /// the volumes are kept between 0 and 1 even if the saved ones are not
+    pub fn set_settings(&mut self,settings:AudioSettings) {
+
+        self.settings = AudioSettings {
+            volume: settings.volume.clamp(0.0,1.0),
+            music_volume: settings.music_volume.clamp(0.0,1.0),
+            effect_volume: settings.effect_volume.clamp(0.0,1.0),
+            muted: settings.muted
+        };
+
+        self.apply_music_volume();
+
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.set_muted(!self.settings.muted);
+    }
+
+    pub fn set_muted(&mut self,muted:bool) {
+        self.settings.muted = muted;
+        self.apply_music_volume();
+    }
+
+    /// add delta to the main volume, it stay between 0 and 1
+    pub fn change_volume(&mut self,delta:f32) {
+        self.settings.volume = (self.settings.volume + delta).clamp(0.0,1.0);
+        self.apply_music_volume();
+    }
+
+    pub fn volume_up(&mut self) { self.change_volume(VOLUME_STEP); }
+    pub fn volume_down(&mut self) { self.change_volume(-VOLUME_STEP); }
+
+
+    fn get_track(&self,track:Track) -> Option<Sound> {
+        match track {
+            Track::Normal => self.music,
+            // a level without hurry music keep its normal one
+            Track::Hurry => self.hurry_music.or(self.music),
+            Track::Star => self.star_music.or(self.music)
+        }
+    }
+
+    fn play_track(&mut self,track:Track) {
+
+        self.stop_music();
+
+        if let Some(sound) = self.get_track(track) {
+            play_sound(sound,PlaySoundParams {
+                looped: true,
+                volume: self.settings.get_music_volume()
+            });
+        }
+
+        self.playing = Some(track);
+
+    }
+
+    /// the music keep playing while muted so it is still in time when unmuted
+    fn apply_music_volume(&self) {
+        if let Some(sound) = self.playing.and_then(|track| self.get_track(track)) {
+            set_sound_volume(sound,self.settings.get_music_volume());
+        }
+    }
+
+}
+
+
+async fn try_load_sound(path:&str) -> Option<Sound> {
+
+    match load_sound(path).await {
+        Ok(sound) => Some(sound),
+        Err(e) => {
+            eprintln!("can't load sound '{}': {}",path,e);
+            None
+        }
+    }
+
+}
@@ -0,0 +1,102 @@
+
+use crate::declaration::{TILE_SIZE, WIDTH};
+use crate::map::Map;
+use crate::state::CAMERA_OFFSET;
+
+use lib_game::GResult;
+
+use macroquad::prelude::*;
+
+use std::time::{Duration, Instant};
+
+
+/// frames drawn before the timing start, the first ones upload the textures
+const WARM_UP_FRAMES: usize = 30;
+
+
+/// average time of one frame
+struct Timing {
+    /// time spent sending the tiles to macroquad
+    draw:   Duration,
+    /// time of the whole frame, the gpu and the vsync included
+    frame:  Duration
+}
+
+
+/// draw the level from its start to its end with the tiles drawn one by one, then with the atlas meshes,
+/// and print the average frame time of both
+pub async fn run(level:&str,frames:usize) -> GResult<()> {
+
+    let mut map = Map::init(level)?;
+    map.load().await?;
+
+    map.set_batched(false)?;
+    let before = measure(&map,frames).await;
+
+    map.set_batched(true)?;
+    let after = measure(&map,frames).await;
+
+    println!("tilemap benchmark on '{}', {} frames",map.get_name(),frames);
+    println!("{:<10} {:>12} {:>12}","","draw (ms)","frame (ms)");
+
+    for (label,timing) in [("per tile",&before),("atlas",&after)] {
+        println!(
+            "{:<10} {:>12.3} {:>12.3}",
+            label,
+            timing.draw.as_secs_f64() * 1000.0,
+            timing.frame.as_secs_f64() * 1000.0
+        );
+    }
+
+    println!("draw speedup: x{:.1}",before.draw.as_secs_f64() / after.draw.as_secs_f64().max(f64::EPSILON));
+
+    Ok(())
+
+}
+
+/// scroll the camera along the whole level, one step each frame
+async fn measure(map:&Map,frames:usize) -> Timing {
+
+    let (cols,_) = map.get_size();
+    let length = (cols as f32 * TILE_SIZE - WIDTH / 2.0).max(0.0);
+
+    let mut draw = Duration::ZERO;
+    let mut frame = Duration::ZERO;
+
+    for ctn in 0..WARM_UP_FRAMES + frames {
+
+        let moved = length * (ctn % frames.max(1)) as f32 / frames.max(1) as f32;
+        let scroll = moved/(WIDTH/2.0)*2.4;
+
+        let camera = Camera2D {
+            zoom: vec2(2.4,2.4),
+            offset: vec2(CAMERA_OFFSET.0 - scroll,CAMERA_OFFSET.1),
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+
+        clear_background(map.get_background());
+        set_camera(&camera);
+
+        map.render_all(&camera,scroll);
+
+        let drawn = start.elapsed();
+
+        next_frame().await;
+
+        if ctn >= WARM_UP_FRAMES {
+            draw += drawn;
+            frame += start.elapsed();
+        }
+
+    }
+
+    let frames = frames.max(1) as u32;
+
+    Timing {
+        draw: draw / frames,
+        frame: frame / frames
+    }
+
+}
@@ -0,0 +1,134 @@
+
+use lib_game::GResult;
+
+use std::env;
+
+
+const USAGE: &str = "usage: game [options]
+
+options:
+  --level <file>      start with this level, a csv, tmx or tmj file, or a level name of the assets directory
+  --assets <dir>      directory of the assets
+  --scale <factor>    size of the window compared to 960x540
+  --fullscreen        start in fullscreen
+  --debug             show the debug overlay
+  --seed <number>     seed of the random generator, the game has no randomness yet so it is only recorded in replays
+  --replay <file>     play the inputs of a replay file instead of the keyboard
+  --record <file>     write the inputs to a replay file when the game close
+  --headless <frames> run the simulation for some frames without drawing, then print the state
+  --bench [frames]    compare the two way to draw the tilemap
+  --help              show this message";
+
+/// level played when no other is given
+pub const DEFAULT_LEVEL: &str = "lvl-1-1";
+
+
+/// everything the game can be told when it is launched
+#[derive(Debug,Clone,PartialEq)]
+pub struct Options {
+    pub level:      Option<String>,
+    pub assets:     Option<String>,
+    pub scale:      f32,
+    pub fullscreen: bool,
+    pub debug:      bool,
+    pub seed:       Option<u64>,
+    pub replay:     Option<String>,
+    pub record:     Option<String>,
+    pub headless:   Option<u64>,
+    pub bench:      Option<usize>
+}
+
+impl Default for Options {
+
+    fn default() -> Self {
+        Self {
+            level: None,
+            assets: None,
+            scale: 1.0,
+            fullscreen: false,
+            debug: false,
+            seed: None,
+            replay: None,
+            record: None,
+            headless: None,
+            bench: None
+        }
+    }
+
+}
+
+impl Options {
+
+    /// options of the command line the game was started with
+    pub fn from_env() -> GResult<Self> {
+        let args: Vec<String> = env::args().skip(1).collect();
+        Self::parse(&args)
+    }
+
+    pub fn parse(args:&[String]) -> GResult<Self> {
+
+        let mut options = Self::default();
+        let mut args = args.iter().peekable();
+
+        while let Some(arg) = args.next() {
+
+            match arg.as_str() {
+                "--level" => options.level = Some(get_value(arg,args.next())?.to_string()),
+                "--assets" => options.assets = Some(get_value(arg,args.next())?.to_string()),
+                "--scale" => {
+                    let scale: f32 = parse_value(arg,args.next())?;
+
+                    if scale.is_nan() || scale <= 0.0 {
+                        bail!("the scale must be more than 0");
+                    }
+
+                    options.scale = scale;
+                },
+                "--fullscreen" => options.fullscreen = true,
+                "--debug" => options.debug = true,
+                "--seed" => options.seed = Some(parse_value(arg,args.next())?),
+                "--replay" => options.replay = Some(get_value(arg,args.next())?.to_string()),
+                "--record" => options.record = Some(get_value(arg,args.next())?.to_string()),
+                "--headless" => options.headless = Some(parse_value(arg,args.next())?),
+                "--bench" => {
+                    // the number of frame is optional
+                    let frames = match args.next_if(|value| !value.starts_with("--")) {
+                        Some(value) => parse_value(arg,Some(value))?,
+                        None => 600
+                    };
+
+                    options.bench = Some(frames);
+                },
+                "--help" | "-h" => {
+                    println!("{}",USAGE);
+                    std::process::exit(0);
+                },
+                _ => bail!("unknown option '{}'\n\n{}",arg,USAGE)
+            }
+
+        }
+
+        Ok(options)
+
+    }
+
+}
+
+
+fn get_value<'a>(option:&str,value:Option<&'a String>) -> GResult<&'a str> {
+    match value {
+        Some(value) => Ok(value),
+        None => bail!("the option '{}' need a value",option)
+    }
+}
+
+fn parse_value<T:std::str::FromStr>(option:&str,value:Option<&String>) -> GResult<T> {
+
+    let value = get_value(option,value)?;
+
+    match value.parse() {
+        Ok(value) => Ok(value),
+        Err(_) => bail!("invalid value '{}' for the option '{}'",value,option)
+    }
+
+}
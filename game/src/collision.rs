@@ -0,0 +1,233 @@
+
+use crate::world::EntityId;
+
+use lib_game::shape::{Rect, Shape};
+use lib_game::vector::Vec2;
+
+
+/// tolerance used so two rect that only touch are not considered overlapping
+const EPSILON: f32 = 0.001;
+/// distance under which a body is still standing on a solid
+const STAND_TOLERANCE: f32 = 0.01;
+
+
+/// what a solid belong to, so the caller know what it hit
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum SolidKind {
+    Tile,
+    Entity(EntityId)
+}
+
+/// a rect that moving body can not go through
+#[derive(Debug,Clone,Copy)]
+pub struct Solid {
+    pub rect:       Rect,
+    pub kind:       SolidKind,
+    /// only stop the bodies that go up into it
+    pub from_below: bool
+}
+
+impl Solid {
+    pub fn new(rect:Rect,kind:SolidKind) -> Self { Self { rect, kind, from_below: false } }
+}
+
+/// a solid that stopped a body, the normal point away from the solid surface
+#[derive(Debug,Clone,Copy)]
+pub struct Contact {
+    pub kind:   SolidKind,
+    pub normal: Vec2
+}
+
+impl Contact {
+    /// the body stand on the solid
+    pub fn is_ground(&self) -> bool { self.normal.y > 0.0 }
+    /// the body hit the solid with is head
+    pub fn is_ceiling(&self) -> bool { self.normal.y < 0.0 }
+    /// the body hit the side of the solid
+    pub fn is_wall(&self) -> bool { self.normal.x != 0.0 }
+}
+
+
+#[derive(Clone,Copy)]
+enum Axis {
+    X,
+    Y
+}
+
+fn get_max_y(rect:&Rect) -> f32 { rect.get_y() + rect.get_height() }
+
+/// return the start and end of a rect on an axis
+fn get_span(rect:&Rect,axis:Axis) -> (f32,f32) {
+    match axis {
+        Axis::X => (rect.get_x(), rect.get_max_x()),
+        Axis::Y => (rect.get_y(), get_max_y(rect))
+    }
+}
+
+/// check if two rect overlap on the axis that is not the one we move on
+fn overlap_across(a:&Rect,b:&Rect,axis:Axis) -> bool {
+
+    let across = match axis {
+        Axis::X => Axis::Y,
+        Axis::Y => Axis::X
+    };
+
+    let (amin,amax) = get_span(a,across);
+    let (bmin,bmax) = get_span(b,across);
+
+    amin < bmax - EPSILON && amax > bmin + EPSILON
+
+}
+
+/// move the shape on one axis and stop it at the closest solid on its path
+fn sweep_axis<'a,I>(shape:&mut Rect,velocity:&mut Vec2,solids:I,axis:Axis,contacts:&mut Vec<Contact>)
+    where I: Iterator<Item=&'a Solid> {
+
+    let speed = match axis {
+        Axis::X => velocity.x,
+        Axis::Y => velocity.y
+    };
+
+    if speed == 0.0 {
+        return;
+    }
+
+    let (smin,smax) = get_span(shape,axis);
+
+    let mut travel = speed.abs();
+    let mut hits: Vec<SolidKind> = Vec::new();
+
+    for solid in solids {
+
+        if solid.from_below && !(matches!(axis,Axis::Y) && speed > 0.0) {
+            continue;
+        }
+
+        if !overlap_across(shape,&solid.rect,axis) {
+            continue;
+        }
+
+        let (cmin,cmax) = get_span(&solid.rect,axis);
+
+        // space between the shape and the solid in the direction of the movement
+        let gap = if speed > 0.0 { cmin - smax } else { smin - cmax };
+
+        // a solid behind the shape or already overlapping it can not stop it
+        if gap < -EPSILON {
+            continue;
+        }
+
+        let gap = gap.max(0.0);
+
+        if gap < travel - EPSILON {
+            travel = gap;
+            hits.clear();
+            hits.push(solid.kind);
+        } else if gap <= travel + EPSILON {
+            hits.push(solid.kind);
+        }
+
+    }
+
+    let moved = travel * speed.signum();
+
+    let normal = match axis {
+        Axis::X => {
+            shape.pos.x += moved;
+            Vec2::new(-speed.signum(),0.0)
+        },
+        Axis::Y => {
+            shape.pos.y += moved;
+            Vec2::new(0.0,-speed.signum())
+        }
+    };
+
+    if !hits.is_empty() {
+
+        match axis {
+            Axis::X => velocity.x = 0.0,
+            Axis::Y => velocity.y = 0.0
+        }
+
+        for kind in hits {
+            contacts.push(Contact { kind, normal });
+        }
+
+    }
+
+}
+
+/// the shape rest on the top of the rect
+pub fn is_standing_on(shape:&Rect,rect:&Rect) -> bool {
+    (shape.get_y() - get_max_y(rect)).abs() <= STAND_TOLERANCE && overlap_across(shape,rect,Axis::Y)
+}
+
+/// move the shape by its velocity, horizontally then vertically, without going through any solid.
+///
+/// The whole path is swept on each axis so a fast body can't tunnel through a thin solid, and
+/// solids that only touch the shape on its side are ignored so it doesn't snag on tile seams.
+/// The velocity of a blocked axis is set to zero and a contact is returned for each solid hit.
+pub fn move_and_collide<'a,I>(shape:&mut Rect,velocity:&mut Vec2,solids:I) -> Vec<Contact>
+    where I: Iterator<Item=&'a Solid> + Clone {
+
+    let mut contacts = Vec::new();
+
+    sweep_axis(shape,velocity,solids.clone(),Axis::X,&mut contacts);
+    sweep_axis(shape,velocity,solids,Axis::Y,&mut contacts);
+
+    contacts
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const TILE: f32 = 16.0;
+
+    fn tile(x:f32,y:f32,width:f32,height:f32) -> Solid { Solid::new(Rect::new(x,y,width,height),SolidKind::Tile) }
+
+    #[test]
+    fn slide_across_a_tile_seam_without_wall_contact() {
+
+        // two tiles side by side, the body walk from the first to the second while pressed on the floor
+        let solids = [tile(0.0,0.0,TILE,TILE),tile(TILE,0.0,TILE,TILE)];
+        let mut shape = Rect::new(2.0,TILE,TILE,TILE);
+
+        for _ in 0..8 {
+
+            let mut velocity = Vec2::new(2.0,-1.0);
+            let contacts = move_and_collide(&mut shape,&mut velocity,solids.iter());
+
+            assert!(!contacts.iter().any(|contact| contact.is_wall()),"snagged at x {}",shape.pos.x);
+            assert!(contacts.iter().any(|contact| contact.is_ground()));
+            assert_eq!(velocity.x,2.0);
+
+        }
+
+        assert_eq!(shape.pos.x,18.0);
+        assert_eq!(shape.pos.y,TILE);
+
+    }
+
+    #[test]
+    fn fast_fall_stop_on_top_of_a_thin_tile() {
+
+        // the fall of one frame is far longer than the solid is thick
+        let solids = [tile(0.0,0.0,TILE,2.0)];
+        let mut shape = Rect::new(0.0,40.0,TILE,TILE);
+        let mut velocity = Vec2::new(0.0,-60.0);
+
+        let contacts = move_and_collide(&mut shape,&mut velocity,solids.iter());
+
+        assert_eq!(shape.pos.y,2.0);
+        assert_eq!(velocity.y,0.0);
+        assert_eq!(contacts.len(),1);
+        assert!(contacts[0].is_ground());
+        assert_eq!(contacts[0].kind,SolidKind::Tile);
+
+    }
+
+}
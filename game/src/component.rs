@@ -0,0 +1,174 @@
+
+use crate::animation::{AnimationPlayer, AnimationState};
+use crate::assets::get_assets_dir;
+use crate::declaration::{NORM_HEIGHT_TILE_SIZE, NORM_WIDTH_TILE_SIZE, TILE_SIZE};
+use crate::entity::get_normalized_position;
+
+use lib_game::GResult;
+use lib_game::vector::Vec2;
+
+use serde::{Deserialize, Serialize};
+
+use macroquad::prelude::{
+    Color,
+    Texture2D,
+    load_texture,
+    WHITE,
+    DrawTextureParams,
+    Rect as r,
+    draw_texture_ex,
+    vec2
+};
+
+
+/// size of the hitbox of an entity, its bottom left corner is the entity position
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct Collider {
+    pub width:      f32,
+    pub height:     f32,
+    /// moving entities stand on it and are stopped by it
+    pub solid:      bool,
+    /// go through every solid without touching it
+    pub ghost:      bool,
+    /// a solid that only stop the bodies coming from below, like a hidden block
+    pub from_below: bool
+}
+
+impl Collider {
+    pub fn new(width:f32,height:f32,solid:bool) -> Self { Self { width, height, solid, ghost: false, from_below: false } }
+}
+
+
+/// texture of an entity and the animation that choose the part of it to draw
+pub struct Sprite {
+    texture:        Texture2D,
+    animation:      AnimationPlayer,
+    draw_info:      DrawTextureParams,
+    pub visible:    bool,
+    /// color the texture is multiplied by, white keep it as it is
+    pub tint:       Color
+}
+
+impl Sprite {
+
+    /// load the png of a sprite and the animation file with the same name next to it
+    pub async fn load(name:&str) -> GResult<Self> {
+
+        let texture = load_texture(&format!("{}/{}.png",get_assets_dir(),name)).await?;
+        let animation = AnimationPlayer::load(&format!("{}/{}.json",get_assets_dir(),name))?;
+
+        // default frame
+        let frame = animation.get_spritesheet().get_current_frame()?;
+
+        let draw_info = DrawTextureParams {
+            dest_size: Some(vec2(NORM_WIDTH_TILE_SIZE,NORM_HEIGHT_TILE_SIZE)),
+            source: Some(r::new(frame.position.x,frame.position.y,frame.size.x,frame.size.y)),
+            rotation: 0.0,
+            flip_x: false,
+            flip_y: true,
+            pivot: None
+        };
+
+        Ok(Self {
+            texture,
+            animation,
+            draw_info,
+            visible: true,
+            tint: WHITE
+        })
+
+    }
+
+    /// change the texture descriptor with the current sprite info
+    pub fn reload(&mut self) {
+
+        let spritesheet = self.animation.get_spritesheet();
+
+        let frame = spritesheet.get_current_frame().unwrap();
+        self.draw_info.flip_x = spritesheet.should_flip();
+        self.draw_info.source = Some(r::new(
+            frame.position.x,
+            frame.position.y,
+            frame.size.x,
+            frame.size.y
+        ));
+
+    }
+
+    /// switch to another clip of the animation, it start from its first frame
+    pub fn play(&mut self,name:&str) {
+        self.animation.play(name).unwrap();
+        self.reload();
+    }
+
+    /// advance the animation by one frame, return true when a one-shot clip just ended
+    pub fn tick(&mut self) -> bool {
+        let finished = self.animation.tick().unwrap();
+        self.reload();
+
+        finished
+    }
+
+    pub fn get_clip(&self) -> &str { self.animation.get_clip() }
+
+    pub fn get_animation(&self) -> AnimationState { self.animation.get_state() }
+
+    /// show the frame of a state taken earlier
+    /// fail when set_animation would fail, the sprite doesn't change
+    pub fn check_animation(&self,state:&AnimationState) -> GResult<()> {
+        self.animation.check_state(state)?;
+
+        Ok(())
+    }
+
+    pub fn set_animation(&mut self,state:&AnimationState) -> GResult<()> {
+        self.animation.set_state(state)?;
+        self.reload();
+
+        Ok(())
+    }
+
+    pub fn is_flipped(&self) -> bool { self.animation.get_spritesheet().should_flip() }
+
+    /// size of the sprite on screen in pixel
+    pub fn set_size(&mut self,width:f32,height:f32) {
+        self.draw_info.dest_size = Some(vec2(
+            width / TILE_SIZE * NORM_WIDTH_TILE_SIZE,
+            height / TILE_SIZE * NORM_HEIGHT_TILE_SIZE
+        ));
+    }
+
+    /// make the sprite face left when flip is true and right otherwise
+    pub fn flip(&mut self,flip:bool) {
+
+        let spritesheet = self.animation.get_spritesheet_mut();
+
+        if spritesheet.should_flip() != flip {
+            spritesheet.flip_sprite();
+        }
+
+    }
+
+    /// draw the sprite head down, like an enemy knocked off the level
+    pub fn set_upside_down(&mut self,upside_down:bool) {
+        // the y axis point up so the texture is always flipped once
+        self.draw_info.flip_y = !upside_down;
+    }
+
+    pub fn is_upside_down(&self) -> bool { !self.draw_info.flip_y }
+
+    pub fn draw(&self,pos:&Vec2) {
+
+        let (nx,ny) = get_normalized_position(pos);
+
+        draw_texture_ex(
+            self.texture,
+            nx,
+            ny,
+            self.tint,
+            self.draw_info.clone()
+        );
+
+    }
+
+}
@@ -0,0 +1,212 @@
+
+use crate::player::Power;
+
+use lib_game::GResult;
+
+use macroquad::prelude::*;
+
+
+/// number of line of the log shown above the prompt
+const LOG_LINES: usize = 12;
+
+const FONT_SIZE: f32 = 18.0;
+const BACKGROUND: Color = Color::new(0.0,0.0,0.0,0.75);
+
+const HELP: &str = "teleport <x> <y> | spawn <goomba|block> <x> <y> | godmode | noclip | setpower <small|big|fire> \
+                    | level <name> | timescale <factor> | unfreeze all | clear";
+
+
+/// entity the console can add to the world
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum SpawnKind {
+    Goomba,
+    Block
+}
+
+/// what can be typed in the console, the positions are in world pixel
+#[derive(Debug,Clone,PartialEq)]
+pub enum Command {
+    Teleport(f32,f32),
+    Spawn(SpawnKind,f32,f32),
+    GodMode,
+    NoClip,
+    SetPower(Power),
+    Level(String),
+    TimeScale(f32),
+    UnfreezeAll,
+    Clear,
+    Help
+}
+
+impl Command {
+
+    pub fn parse(line:&str) -> GResult<Self> {
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        let command = match words.as_slice() {
+            ["teleport",x,y] => Command::Teleport(parse_number(x)?,parse_number(y)?),
+            ["spawn",kind,x,y] => {
+
+                let kind = match *kind {
+                    "goomba" => SpawnKind::Goomba,
+                    "block" => SpawnKind::Block,
+                    _ => bail!("can't spawn '{}'",kind)
+                };
+
+                Command::Spawn(kind,parse_number(x)?,parse_number(y)?)
+
+            },
+            ["godmode"] => Command::GodMode,
+            ["noclip"] => Command::NoClip,
+            ["setpower",name] => match Power::from_name(name) {
+                Some(power) => Command::SetPower(power),
+                None => bail!("unknown power '{}'",name)
+            },
+            ["level",name] => Command::Level(name.to_string()),
+            ["timescale",scale] => {
+
+                let scale = parse_number(scale)?;
+
+                if !(0.0..=8.0).contains(&scale) {
+                    bail!("the time scale must be between 0 and 8");
+                }
+
+                Command::TimeScale(scale)
+
+            },
+            ["unfreeze","all"] => Command::UnfreezeAll,
+            ["clear"] => Command::Clear,
+            ["help"] => Command::Help,
+            [] => bail!("no command"),
+            [name,..] => bail!("unknown command or wrong arguments '{}', try help",name)
+        };
+
+        Ok(command)
+
+    }
+
+    pub fn get_help() -> &'static str { HELP }
+
+}
+
+fn parse_number(word:&str) -> GResult<f32> {
+    match word.parse() {
+        Ok(value) => Ok(value),
+        Err(_) => bail!("'{}' is not a number",word)
+    }
+}
+
+
+/// text prompt opened with the backtick key
+#[derive(Default)]
+pub struct Console {
+    open:       bool,
+    line:       String,
+    /// the commands typed and their answer, the last one at the end
+    log:        Vec<String>,
+    history:    Vec<String>,
+    /// position in the history while it is browsed with the arrows
+    browsing:   Option<usize>
+}
+
+impl Console {
+
+    pub fn new() -> Self { Self::default() }
+
+    pub fn is_open(&self) -> bool { self.open }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.line.clear();
+        self.browsing = None;
+    }
+
+    pub fn print(&mut self,text:&str) {
+        self.log.extend(text.lines().map(str::to_string));
+    }
+
+    pub fn clear(&mut self) { self.log.clear(); }
+
+    /// read the keys typed during the frame, return the line when enter is pressed
+    pub fn handle_input(&mut self) -> Option<String> {
+
+        // macroquad keep the characters until they are read, and give the last typed first
+        let mut typed = Vec::new();
+        while let Some(character) = get_char_pressed() {
+            typed.push(character);
+        }
+
+        if !self.open {
+            return None;
+        }
+
+        for character in typed.into_iter().rev() {
+            if !character.is_control() && character != '`' {
+                self.line.push(character);
+            }
+        }
+
+        if is_key_pressed(KeyCode::Backspace) {
+            self.line.pop();
+        }
+
+        if is_key_pressed(KeyCode::Up) && !self.history.is_empty() {
+            let pos = self.browsing.map_or(self.history.len() - 1,|pos| pos.saturating_sub(1));
+            self.browse(Some(pos));
+        }
+
+        if is_key_pressed(KeyCode::Down) {
+            let pos = self.browsing.map(|pos| pos + 1).filter(|pos| *pos < self.history.len());
+            self.browse(pos);
+        }
+
+        if !is_key_pressed(KeyCode::Enter) || self.line.trim().is_empty() {
+            return None;
+        }
+
+        let line = std::mem::take(&mut self.line);
+
+        self.log.push(format!("> {}",line));
+        self.history.push(line.clone());
+        self.browsing = None;
+
+        Some(line)
+
+    }
+
+    fn browse(&mut self,pos:Option<usize>) {
+
+        self.browsing = pos;
+        self.line = match pos {
+            Some(pos) => self.history[pos].clone(),
+            None => String::new()
+        };
+
+    }
+
+    /// the log and the prompt on the top of the screen
+    pub fn render(&self) {
+
+        if !self.open {
+            return;
+        }
+
+        let line_height = FONT_SIZE * 1.2;
+        let height = line_height * (LOG_LINES + 1) as f32 + 10.0;
+
+        set_default_camera();
+
+        draw_rectangle(0.0,0.0,screen_width(),height,BACKGROUND);
+
+        let start = self.log.len().saturating_sub(LOG_LINES);
+
+        for (i,line) in self.log[start..].iter().enumerate() {
+            draw_text(line,10.0,line_height * (i as f32 + 1.0),FONT_SIZE,LIGHTGRAY);
+        }
+
+        draw_text(&format!("> {}_",self.line),10.0,line_height * (LOG_LINES + 1) as f32,FONT_SIZE,WHITE);
+
+    }
+
+}
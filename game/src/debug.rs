@@ -0,0 +1,158 @@
+
+use crate::entity::{get_normalized_position, get_view};
+use crate::map::Map;
+use crate::world::{Behaviour, EntityId, World};
+
+use lib_game::shape::{Rect, Shape};
+use lib_game::vector::Vec2;
+
+use macroquad::prelude::*;
+
+
+/// the velocity line show where the entity would be after this number of frame
+const VELOCITY_SCALE: f32 = 8.0;
+
+const WALL_COLOR: Color = Color::new(1.0,0.2,0.2,0.8);
+const PLAYER_COLOR: Color = Color::new(0.2,1.0,0.2,1.0);
+const ENEMY_COLOR: Color = Color::new(1.0,0.6,0.0,1.0);
+const BLOCK_COLOR: Color = Color::new(1.0,1.0,0.2,1.0);
+const PLATFORM_COLOR: Color = Color::new(0.2,0.8,1.0,1.0);
+const COIN_COLOR: Color = Color::new(1.0,0.8,0.4,1.0);
+const STAR_COLOR: Color = Color::new(1.0,0.4,0.8,1.0);
+const FLOWER_COLOR: Color = Color::new(1.0,0.3,0.3,1.0);
+const FIREBALL_COLOR: Color = Color::new(1.0,0.5,0.1,1.0);
+const PANEL_COLOR: Color = Color::new(0.0,0.0,0.0,0.6);
+
+const FONT_SIZE: f32 = 18.0;
+
+
+/// screen position of a point of the world
+fn to_screen(camera:&Camera2D,x:f32,y:f32) -> macroquad::math::Vec2 {
+    let (nx,ny) = get_normalized_position(&Vec2::new(x,y));
+    camera.world_to_screen(vec2(nx,ny))
+}
+
+fn draw_rect(camera:&Camera2D,rect:&Rect,color:Color) {
+
+    let a = to_screen(camera,rect.get_x(),rect.get_y());
+    let b = to_screen(camera,rect.get_x() + rect.get_width(),rect.get_y() + rect.get_height());
+
+    draw_rectangle_lines(a.x.min(b.x),a.y.min(b.y),(a.x - b.x).abs(),(a.y - b.y).abs(),1.0,color);
+
+}
+
+
+/// hitboxes, velocities and player state drawn on top of the game
+pub struct DebugOverlay {
+    visible:    bool
+}
+
+impl DebugOverlay {
+
+    pub fn new(visible:bool) -> Self { Self { visible } }
+
+    pub fn toggle(&mut self) { self.visible = !self.visible; }
+
+    /// everything is drawn in screen space, the camera of the level is only used to place the boxes
+    pub fn render(&self,camera:&Camera2D,world:&World,map:&Map,frame:u64) {
+
+        if !self.visible {
+            return;
+        }
+
+        set_default_camera();
+
+        let (left,right) = get_view(camera);
+
+        // the tiles that stop the entities
+        for solid in map.get_solids().iter() {
+            if solid.rect.get_max_x() >= left && solid.rect.get_x() <= right {
+                draw_rect(camera,&solid.rect,WALL_COLOR);
+            }
+        }
+
+        for id in world.active_entities() {
+            self.render_entity(camera,world,id);
+        }
+
+        self.render_panel(world,frame);
+
+        set_camera(camera);
+
+    }
+
+    /// hitbox of the entity and a line along its velocity
+    fn render_entity(&self,camera:&Camera2D,world:&World,id:EntityId) {
+
+        let rect = match world.get_rect(id) {
+            Some(rect) => rect,
+            None => return
+        };
+
+        let color = match world.get_behaviour(id) {
+            Some(Behaviour::Player(_)) => PLAYER_COLOR,
+            Some(Behaviour::Goomba(_)) => ENEMY_COLOR,
+            Some(Behaviour::MysteryBlock(_)) => BLOCK_COLOR,
+            Some(Behaviour::Platform(_)) => PLATFORM_COLOR,
+            Some(Behaviour::Coin(_)) => COIN_COLOR,
+            Some(Behaviour::Star(_)) => STAR_COLOR,
+            Some(Behaviour::FireFlower(_)) => FLOWER_COLOR,
+            Some(Behaviour::Fireball(_)) => FIREBALL_COLOR,
+            None => WHITE
+        };
+
+        draw_rect(camera,&rect,color);
+
+        if let Some(velocity) = world.get_velocity(id) {
+
+            let cx = rect.get_x() + rect.get_width() / 2.0;
+            let cy = rect.get_y() + rect.get_height() / 2.0;
+
+            let from = to_screen(camera,cx,cy);
+            let to = to_screen(camera,cx + velocity.x * VELOCITY_SCALE,cy + velocity.y * VELOCITY_SCALE);
+
+            draw_line(from.x,from.y,to.x,to.y,2.0,color);
+
+        }
+
+    }
+
+    /// frame rate and state of the player in the top right corner
+    fn render_panel(&self,world:&World,frame:u64) {
+
+        let mut lines = vec![
+            format!("FPS {}",get_fps()),
+            format!("frame time {:.2} ms",get_frame_time() * 1000.0),
+            format!("frame {}",frame)
+        ];
+
+        let player = world.get_player();
+
+        if let Some(player) = player.and_then(|id| world.get_behaviour(id)).and_then(|behaviour| behaviour.as_player()) {
+            lines.push(format!("status {:?}",player.get_status()));
+            lines.push(format!("power {:?}",player.get_power()));
+            lines.push(format!("can_jump {}",player.can_jump()));
+            lines.push(format!("jumping {}",player.is_jumping()));
+            lines.push(format!("fall_ctn {}",player.get_fall_ctn()));
+        }
+
+        if let Some(id) = player {
+            if let (Some(pos),Some(velocity)) = (world.get_position(id),world.get_velocity(id)) {
+                lines.push(format!("position {:.1} {:.1}",pos.x,pos.y));
+                lines.push(format!("velocity {:.2} {:.2}",velocity.x,velocity.y));
+            }
+        }
+
+        let line_height = FONT_SIZE * 1.2;
+        let width = 220.0;
+        let x = screen_width() - width - 10.0;
+
+        draw_rectangle(x,10.0,width,line_height * lines.len() as f32 + 10.0,PANEL_COLOR);
+
+        for (i,line) in lines.iter().enumerate() {
+            draw_text(line,x + 8.0,10.0 + line_height * (i as f32 + 1.0),FONT_SIZE,WHITE);
+        }
+
+    }
+
+}
@@ -1,7 +1,4 @@
 
-pub const ASSETS_DIR: &str = "../assets";
-pub const TILE_DIR: &str = "../assets/tiles";
-
 pub const WIDTH:f32 = 960.0;
 pub const HEIGHT:f32 = 540.0;
 
@@ -9,7 +6,3 @@ pub const TILE_SIZE:f32 = 16.0;
 pub const NORM_WIDTH_TILE_SIZE:f32 = TILE_SIZE/ (WIDTH/2.0);
 pub const NORM_HEIGHT_TILE_SIZE:f32 = TILE_SIZE/ (HEIGHT/2.0);
 
-
-pub const GRAVITY: f32 = 10.0;
-
-
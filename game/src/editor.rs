@@ -0,0 +1,601 @@
+
+use crate::declaration::{
+    TILE_SIZE,
+    WIDTH,
+    NORM_HEIGHT_TILE_SIZE,
+    NORM_WIDTH_TILE_SIZE
+};
+use crate::entity::{get_normalized_position, get_world_position};
+use crate::level::{BlockData, BlockKind, Facing, GoombaData, LevelData, Point};
+use crate::map::{EMPTY_TILE, Map};
+
+use lib_game::GResult;
+use lib_game::vector::Vec2;
+
+use macroquad::prelude::*;
+
+
+/// number of pixel the camera move each frame while scrolling
+const SCROLL_SPEED: f32 = 8.0;
+
+
+/// what a click do in the editor
+#[derive(Debug,Clone,Copy,PartialEq)]
+enum Tool {
+    Tile,
+    Goomba,
+    Block,
+    Coin,
+    Move,
+    PlayerStart,
+    Goal
+}
+
+impl Tool {
+
+    fn get_name(&self) -> &'static str {
+        match self {
+            Tool::Tile => "tile",
+            Tool::Goomba => "goomba",
+            Tool::Block => "block",
+            Tool::Coin => "coin",
+            Tool::Move => "move",
+            Tool::PlayerStart => "player start",
+            Tool::Goal => "goal"
+        }
+    }
+
+}
+
+
+#[derive(Debug,Clone,Copy)]
+struct TileChange {
+    col:        usize,
+    row:        usize,
+    before:     i32,
+    after:      i32
+}
+
+/// one action of the user, it can be undone and redone
+enum Edit {
+    /// every cell painted while the mouse button was down
+    Tiles(Vec<TileChange>),
+    Entities {
+        before: Box<LevelData>,
+        after:  Box<LevelData>
+    }
+}
+
+/// entity that follow the mouse with the move tool
+#[derive(Debug,Clone,Copy)]
+enum Grab {
+    Goomba(usize),
+    Block(usize),
+    Coin(usize)
+}
+
+
+/// paint the tiles and place the entities of the level from the game itself
+pub struct Editor {
+    active:         bool,
+    tool:           Tool,
+    /// id of the tile painted by the tile tool
+    tile:           i32,
+    /// direction of the next goomba placed
+    facing:         Facing,
+    /// kind of the next block placed
+    block:          BlockKind,
+    undo:           Vec<Edit>,
+    redo:           Vec<Edit>,
+    /// cells painted since the mouse button was pressed
+    stroke:         Vec<TileChange>,
+    /// entities before the mouse button was pressed
+    snapshot:       Option<LevelData>,
+    grab:           Option<Grab>,
+    /// the entities changed since the editor was opened
+    changed:        bool,
+    message:        String
+}
+
+impl Editor {
+
+    pub fn new(map:&Map) -> Self {
+        Self {
+            active: false,
+            tool: Tool::Tile,
+            tile: map.get_palette().keys().next().copied().unwrap_or(EMPTY_TILE),
+            facing: Facing::Left,
+            block: BlockKind::Mystery,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            stroke: Vec::new(),
+            snapshot: None,
+            grab: None,
+            changed: false,
+            message: String::new()
+        }
+    }
+
+    pub fn is_active(&self) -> bool { self.active }
+
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+        self.message.clear();
+    }
+
+    /// return true once if the entities changed, the world must then be spawned again
+    pub fn take_changed(&mut self) -> bool { std::mem::take(&mut self.changed) }
+
+
+    pub fn handle_input(&mut self,camera:&mut Camera2D,map:&mut Map,level:&mut LevelData) {
+
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+
+        if ctrl {
+
+            if is_key_pressed(KeyCode::Z) {
+                self.undo(map,level);
+            }
+
+            if is_key_pressed(KeyCode::Y) {
+                self.redo(map,level);
+            }
+
+            if is_key_pressed(KeyCode::S) {
+                self.message = match save(map,level) {
+                    Ok(()) => format!("saved {}",map.get_name()),
+                    Err(e) => {
+                        eprintln!("can't save the level: {}",e);
+                        format!("save failed: {}",e)
+                    }
+                };
+            }
+
+            return;
+
+        }
+
+        self.scroll(camera);
+
+        let mouse = get_mouse_position(camera);
+
+        self.choose_tool(map,&mouse);
+
+        match self.tool {
+            Tool::Tile => self.paint(map,&mouse),
+            _ => self.edit_entities(map,level,&mouse)
+        }
+
+    }
+
+    fn scroll(&mut self,camera:&mut Camera2D) {
+
+        let mut speed = SCROLL_SPEED;
+
+        if is_key_down(KeyCode::LeftShift) {
+            speed *= 4.0;
+        }
+
+        if is_key_down(KeyCode::A) || is_key_down(KeyCode::Left) {
+            camera.offset.x += speed/(WIDTH/2.0)*2.4;
+        }
+
+        if is_key_down(KeyCode::D) || is_key_down(KeyCode::Right) {
+            camera.offset.x -= speed/(WIDTH/2.0)*2.4;
+        }
+
+        set_camera(camera);
+
+    }
+
+    fn choose_tool(&mut self,map:&Map,mouse:&Vec2) {
+
+        let tools = [
+            (KeyCode::Key1,Tool::Tile),
+            (KeyCode::Key2,Tool::Goomba),
+            (KeyCode::Key3,Tool::Block),
+            (KeyCode::Key4,Tool::Coin),
+            (KeyCode::Key5,Tool::Move),
+            (KeyCode::Key6,Tool::PlayerStart),
+            (KeyCode::Key7,Tool::Goal)
+        ];
+
+        for (key,tool) in tools {
+            if is_key_pressed(key) {
+                self.tool = tool;
+            }
+        }
+
+        let palette = map.get_palette();
+
+        if is_key_pressed(KeyCode::RightBracket) {
+            if let Some(id) = palette.range(self.tile + 1..).next().map(|(id,_)| *id) {
+                self.tile = id;
+            }
+        }
+
+        if is_key_pressed(KeyCode::LeftBracket) {
+            if let Some(id) = palette.range(..self.tile).next_back().map(|(id,_)| *id) {
+                self.tile = id;
+            }
+        }
+
+        // pick the tile under the mouse
+        if is_key_pressed(KeyCode::Q) {
+            if let Some((col,row)) = map.get_cell(mouse) {
+                let id = map.get_tile(col,row);
+                if id != EMPTY_TILE {
+                    self.tile = id;
+                }
+            }
+        }
+
+        if is_key_pressed(KeyCode::R) {
+            self.facing = match self.facing {
+                Facing::Left => Facing::Right,
+                Facing::Right => Facing::Left
+            };
+        }
+
+        if is_key_pressed(KeyCode::T) {
+            self.block = match self.block {
+                BlockKind::Mystery => BlockKind::Hidden,
+                BlockKind::Hidden => BlockKind::Coins,
+                BlockKind::Coins => BlockKind::Star,
+                BlockKind::Star => BlockKind::Flower,
+                BlockKind::Flower => BlockKind::Mystery
+            };
+        }
+
+    }
+
+    /// left click paint the selected tile, right click erase
+    fn paint(&mut self,map:&mut Map,mouse:&Vec2) {
+
+        let id = if is_mouse_button_down(MouseButton::Left) {
+            Some(self.tile)
+        } else if is_mouse_button_down(MouseButton::Right) {
+            Some(EMPTY_TILE)
+        } else {
+            None
+        };
+
+        if let (Some(id),Some((col,row))) = (id,map.get_cell(mouse)) {
+
+            match map.set_tile(col,row,id) {
+                Ok(before) if before != id => {
+                    self.stroke.push(TileChange { col, row, before, after: id });
+                    self.rebuild(map);
+                },
+                Ok(_) => {},
+                Err(e) => self.message = e.to_string()
+            }
+
+        }
+
+        if id.is_none() && !self.stroke.is_empty() {
+            let stroke = std::mem::take(&mut self.stroke);
+            self.push(Edit::Tiles(stroke));
+        }
+
+    }
+
+    fn edit_entities(&mut self,map:&Map,level:&mut LevelData,mouse:&Vec2) {
+
+        let left = is_mouse_button_pressed(MouseButton::Left);
+        let right = is_mouse_button_pressed(MouseButton::Right);
+
+        if left || right {
+            self.snapshot = Some(level.clone());
+        }
+
+        // entities are placed on the grid
+        if let Some((col,row)) = map.get_cell(mouse) {
+            self.place(level,mouse,&map.get_cell_position(col,row),left,right);
+        }
+
+        let released = is_mouse_button_released(MouseButton::Left) || is_mouse_button_released(MouseButton::Right);
+
+        if released {
+
+            self.grab = None;
+
+            if let Some(before) = self.snapshot.take() {
+                if before != *level {
+                    self.push(Edit::Entities { before: Box::new(before), after: Box::new(level.clone()) });
+                }
+            }
+
+        }
+
+    }
+
+    /// apply the entity tool to the cell under the mouse
+    fn place(&mut self,level:&mut LevelData,mouse:&Vec2,cell:&Vec2,left:bool,right:bool) {
+
+        match self.tool {
+
+            Tool::Goomba if left && find_goomba(level,mouse).is_none() => {
+                level.goombas.push(GoombaData {
+                    x: cell.x,
+                    y: cell.y,
+                    direction: self.facing,
+                    wake_x: None
+                });
+            },
+            Tool::Goomba if right => {
+                if let Some(i) = find_goomba(level,mouse) {
+                    level.goombas.remove(i);
+                }
+            },
+            Tool::Block if left && find_block(level,mouse).is_none() => {
+                level.blocks.push(BlockData { x: cell.x, y: cell.y, kind: self.block });
+            },
+            Tool::Block if right => {
+                if let Some(i) = find_block(level,mouse) {
+                    level.blocks.remove(i);
+                }
+            },
+            Tool::Coin if left && find_coin(level,mouse).is_none() => {
+                level.coins.push(Point { x: cell.x, y: cell.y });
+            },
+            Tool::Coin if right => {
+                if let Some(i) = find_coin(level,mouse) {
+                    level.coins.remove(i);
+                }
+            },
+            Tool::Move => {
+
+                if left {
+                    self.grab = find_goomba(level,mouse).map(Grab::Goomba)
+                        .or_else(|| find_block(level,mouse).map(Grab::Block))
+                        .or_else(|| find_coin(level,mouse).map(Grab::Coin));
+                }
+
+                match self.grab {
+                    Some(Grab::Goomba(i)) => {
+                        level.goombas[i].x = cell.x;
+                        level.goombas[i].y = cell.y;
+                    },
+                    Some(Grab::Block(i)) => {
+                        level.blocks[i].x = cell.x;
+                        level.blocks[i].y = cell.y;
+                    },
+                    Some(Grab::Coin(i)) => {
+                        level.coins[i] = Point { x: cell.x, y: cell.y };
+                    },
+                    None => {}
+                }
+
+            },
+            Tool::PlayerStart if left => {
+                level.player = Point { x: cell.x, y: cell.y };
+            },
+            Tool::Goal if left => {
+                level.goal = cell.x;
+            },
+            _ => {}
+
+        }
+
+    }
+
+    fn push(&mut self,edit:Edit) {
+
+        if let Edit::Entities { .. } = edit {
+            self.changed = true;
+        }
+
+        self.undo.push(edit);
+        self.redo.clear();
+
+    }
+
+    fn undo(&mut self,map:&mut Map,level:&mut LevelData) {
+
+        if let Some(edit) = self.undo.pop() {
+            self.apply(&edit,map,level,true);
+            self.redo.push(edit);
+        }
+
+    }
+
+    fn redo(&mut self,map:&mut Map,level:&mut LevelData) {
+
+        if let Some(edit) = self.redo.pop() {
+            self.apply(&edit,map,level,false);
+            self.undo.push(edit);
+        }
+
+    }
+
+    /// put back the level as it was before the edit when undo is true, or after it otherwise
+    fn apply(&mut self,edit:&Edit,map:&mut Map,level:&mut LevelData,undo:bool) {
+
+        match edit {
+
+            Edit::Tiles(changes) => {
+
+                let changes: Vec<(usize,usize,i32)> = if undo {
+                    changes.iter().rev().map(|c| (c.col,c.row,c.before)).collect()
+                } else {
+                    changes.iter().map(|c| (c.col,c.row,c.after)).collect()
+                };
+
+                for (col,row,id) in changes {
+                    if let Err(e) = map.set_tile(col,row,id) {
+                        self.message = e.to_string();
+                    }
+                }
+
+                // the tiles are created once for the whole edit, not for each cell
+                self.rebuild(map);
+
+            },
+            Edit::Entities { before, after } => {
+                *level = if undo { (**before).clone() } else { (**after).clone() };
+                self.changed = true;
+            }
+
+        }
+
+    }
+
+
+    fn rebuild(&mut self,map:&mut Map) {
+        if let Err(e) = map.rebuild_main_layer() {
+            self.message = e.to_string();
+        }
+    }
+
+    /// draw the grid, the entities of the level and the editor info on top of the map
+    pub fn render(&self,camera:&Camera2D,map:&Map,level:&LevelData) {
+
+        let (cols,rows) = map.get_size();
+        let thickness = 0.002;
+        let grid_color = Color::new(1.0,1.0,1.0,0.25);
+
+        let (left,bottom) = get_normalized_position(&Vec2::new(0.0,TILE_SIZE));
+        let (right,top) = get_normalized_position(&Vec2::new(cols as f32 * TILE_SIZE,(rows + 1) as f32 * TILE_SIZE));
+
+        for col in 0..=cols {
+            let x = left + col as f32 * NORM_WIDTH_TILE_SIZE;
+            draw_line(x,bottom,x,top,thickness,grid_color);
+        }
+
+        for row in 0..=rows {
+            let y = bottom + row as f32 * NORM_HEIGHT_TILE_SIZE;
+            draw_line(left,y,right,y,thickness,grid_color);
+        }
+
+        for goomba in level.goombas.iter() {
+            draw_marker(goomba.x,goomba.y,BROWN);
+        }
+
+        for block in level.blocks.iter() {
+
+            let color = match block.kind {
+                BlockKind::Mystery => GOLD,
+                BlockKind::Hidden => PURPLE,
+                BlockKind::Coins => ORANGE,
+                BlockKind::Star => PINK,
+                BlockKind::Flower => RED
+            };
+
+            draw_marker(block.x,block.y,color);
+
+        }
+
+        for coin in level.coins.iter() {
+            draw_marker(coin.x,coin.y,YELLOW);
+        }
+
+        for platform in level.platforms.iter() {
+            for i in 0..platform.width {
+                draw_marker(platform.x + i as f32 * TILE_SIZE,platform.y,LIME);
+            }
+        }
+
+        draw_marker(level.player.x,level.player.y,RED);
+
+        for checkpoint in level.checkpoints.iter() {
+            let (x,_) = get_normalized_position(&Vec2::new(checkpoint.x,0.0));
+            draw_line(x,bottom,x,top,thickness*2.0,SKYBLUE);
+        }
+
+        let (goal,_) = get_normalized_position(&Vec2::new(level.goal,0.0));
+        draw_line(goal,bottom,goal,top,thickness*2.0,GREEN);
+
+        // cell under the mouse
+        if let Some((col,row)) = map.get_cell(&get_mouse_position(camera)) {
+            let pos = map.get_cell_position(col,row);
+            let (x,y) = get_normalized_position(&pos);
+            draw_rectangle_lines(x,y,NORM_WIDTH_TILE_SIZE,NORM_HEIGHT_TILE_SIZE,thickness*2.0,WHITE);
+        }
+
+        self.render_info(camera,map);
+
+    }
+
+    fn render_info(&self,camera:&Camera2D,map:&Map) {
+
+        set_default_camera();
+
+        draw_rectangle(0.0,0.0,screen_width(),74.0,Color::new(0.0,0.0,0.0,0.6));
+
+        if let Some(kind) = map.get_palette().get(&self.tile) {
+            draw_texture_ex(kind.texture,10.0,10.0,WHITE,DrawTextureParams {
+                dest_size: Some(vec2(32.0,32.0)),
+                ..Default::default()
+            });
+        }
+
+        let facing = match self.facing {
+            Facing::Left => "left",
+            Facing::Right => "right"
+        };
+
+        let info = format!(
+            "EDITOR  layer: {}  tool: {}  tile: {}  goomba: {}  block: {}  undo: {}  {}",
+            map.get_main_layer_name(),
+            self.tool.get_name(),
+            self.tile,
+            facing,
+            self.block.get_name(),
+            self.undo.len(),
+            self.message
+        );
+
+        draw_text(&info,52.0,32.0,20.0,WHITE);
+        draw_text(
+            "1-7 tool  [ ] Q tile  R goomba direction  T block kind  A D scroll  ctrl+Z undo  ctrl+Y redo  ctrl+S save  TAB play",
+            10.0,
+            62.0,
+            16.0,
+            LIGHTGRAY
+        );
+
+        set_camera(camera);
+
+    }
+
+}
+
+
+/// write the tiles and the entities back to the files of the level
+fn save(map:&Map,level:&LevelData) -> GResult<()> {
+
+    map.save()?;
+    level.save(&map.get_file("json"))?;
+
+    Ok(())
+
+}
+
+/// position of the mouse in the level
+fn get_mouse_position(camera:&Camera2D) -> Vec2 {
+    let pos = camera.screen_to_world(mouse_position().into());
+    get_world_position(pos.x,pos.y)
+}
+
+fn contains(x:f32,y:f32,pos:&Vec2) -> bool {
+    pos.x >= x && pos.x < x + TILE_SIZE && pos.y >= y && pos.y < y + TILE_SIZE
+}
+
+fn find_goomba(level:&LevelData,pos:&Vec2) -> Option<usize> {
+    level.goombas.iter().position(|goomba| contains(goomba.x,goomba.y,pos))
+}
+
+fn find_block(level:&LevelData,pos:&Vec2) -> Option<usize> {
+    level.blocks.iter().position(|block| contains(block.x,block.y,pos))
+}
+
+fn find_coin(level:&LevelData,pos:&Vec2) -> Option<usize> {
+    level.coins.iter().position(|coin| contains(coin.x,coin.y,pos))
+}
+
+/// square where an entity will spawn
+fn draw_marker(x:f32,y:f32,color:Color) {
+    let (nx,ny) = get_normalized_position(&Vec2::new(x,y));
+    draw_rectangle(nx,ny,NORM_WIDTH_TILE_SIZE,NORM_HEIGHT_TILE_SIZE,Color::new(color.r,color.g,color.b,0.7));
+}
@@ -1,7 +1,6 @@
 
 use crate::declaration::{
     TILE_SIZE,
-    ASSETS_DIR,
     WIDTH,
     HEIGHT,
     NORM_HEIGHT_TILE_SIZE,
@@ -9,26 +8,29 @@ use crate::declaration::{
 };
 
 use lib_game::GResult;
-use lib_game::shape::{Rect, Shape};
-use lib_game::tile::TileInfo;
+use lib_game::shape::Rect;
 use lib_game::vector::Vec2;
-use lib_game::collision;
 use lib_game::Direction;
 
-use lib_game::sprite::SpriteSheet;
-
+use serde::{Deserialize, Serialize};
 
 use macroquad::prelude::{
+    Camera2D,
     Texture2D,
-    load_texture,
+    screen_width,
+    screen_height,
     WHITE,
     DrawTextureParams,
-    Rect as r,
     draw_texture_ex,
     vec2
 };
 
-use crate::map::TileMap;
+use crate::audio::SoundEffect;
+use crate::component::{Collider, Sprite};
+use crate::level::{BlockKind, Facing};
+use crate::map::TileKind;
+use crate::physics::PhysicsProfile;
+use crate::world::{Behaviour, EntityId, World};
 
 // Basic trait and utility ==============================================
 
@@ -38,28 +40,36 @@ pub trait Entity {
 
 }
 
-pub trait SpriteUser {
-    /// change the texture descriptor with the current sprite info
-    fn reload_sprite(&mut self);
+/// return the pixel world data normalized between -1 and 1
+pub fn get_normalized_position(pos:&Vec2) -> (f32,f32) {
+
+    (
+        (pos.x) / (WIDTH/2.0) - 1.0,
+        (pos.y) / (HEIGHT/2.0) - 1.0
+    )
 
 }
 
-pub trait Dynamic<T> {
+/// return the pixel world position of a normalized one
+pub fn get_world_position(nx:f32,ny:f32) -> Vec2 {
 
-    /// reset to default value the data of the struct
-    fn reset(&mut self);
-    /// perform calculation before the drawing call
-    fn update(&mut self,entity:T);
+    Vec2::new(
+        (nx + 1.0) * (WIDTH/2.0),
+        (ny + 1.0) * (HEIGHT/2.0)
+    )
 
 }
 
-/// return the pixel world data normalized between -1 and 1
-pub fn get_normalized_position(pos:&Vec2) -> (f32,f32) {
+/// lowest and highest pixel world x seen by the camera
+pub fn get_view(camera:&Camera2D) -> (f32,f32) {
 
-    (
-        (pos.x) / (WIDTH/2.0) - 1.0,
-        (pos.y) / (HEIGHT/2.0) - 1.0
-    )
+    let left = camera.screen_to_world(vec2(0.0,0.0));
+    let right = camera.screen_to_world(vec2(screen_width(),screen_height()));
+
+    let left = get_world_position(left.x,left.y).x;
+    let right = get_world_position(right.x,right.y).x;
+
+    (left.min(right),left.max(right))
 
 }
 
@@ -76,23 +86,21 @@ pub struct Tile {
 
 impl Tile {
     
-    pub async fn new(info:&TileInfo,x:f32,y:f32) -> GResult<Self> {
-        
-        let tex = load_texture(info.get_src()).await?;
+    pub fn new(kind:&TileKind,x:f32,y:f32) -> Self {
 
-        Ok(Self {
-            texture: tex,
+        Self {
+            texture: kind.texture,
             shape: Rect::new(x,y,TILE_SIZE,TILE_SIZE),
             draw_info: DrawTextureParams {
                 dest_size: Some(vec2(NORM_WIDTH_TILE_SIZE,NORM_HEIGHT_TILE_SIZE)),
-                source: None,
+                source: kind.source,
                 rotation: 0.0,
                 flip_x: false,
                 flip_y: true,
                 pivot: None
             },
-            wall: info.is_a_wall()
-        })
+            wall: kind.wall
+        }
 
     }
 
@@ -128,386 +136,822 @@ impl Entity for Tile {
 
 // Enemy =============================
 
-const GVELOCITY: f32 = 1.0;
+
+/// every field of a goomba that change while it walk
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct GoombaState {
+    pub direction:  Facing,
+    pub dead:       bool,
+    pub disappear:  bool,
+    pub freeze:     bool,
+    pub fall_ctn:   u8,
+    #[serde(default)]
+    pub knocked:    bool,
+    #[serde(default)]
+    pub knock_velocity: f32
+}
 
 
 pub struct Goomba {
     // start value
     spos:           Vec2,
     sdirection:     Direction,
+    swake:          Option<f32>, // x position the player must reach before it start to walk
     //
-    sfreeze:        bool, // should the it wait the player arrived at a certain location
-    shape:          Rect,
-    texture:        Texture2D,
-    draw_info:      DrawTextureParams,
-    spritesheet:    SpriteSheet,
-    velocity:       Vec2,
     direction:      Direction,
-    walk_frame_cnt: u8,
-    die_frame_cnt:  u8,
     dead:           bool,
     disappear:      bool,
     freeze:         bool,
     fall_ctn:       u8,
+    /// knocked off by the star, it fall through everything upside down
+    knocked:        bool,
+    knock_velocity: f32
 }
 
 impl Goomba {
 
-    pub async fn new(sx:f32,sy:f32,direction:Direction,freeze:bool) -> GResult<Self> {
-
-        let p = format!("{}/goomba.png",ASSETS_DIR);
-
-        let tex = load_texture(&p).await?;
-        let mut spritesheet = SpriteSheet::new(TILE_SIZE,TILE_SIZE);
-
-        spritesheet.add_sprite("walk",2,0.0,0.0,1.0)?;
-        spritesheet.add_sprite("dead",1,34.0,0.0,0.0)?;
-
-        // default frame
-        let frame = spritesheet.get_current_frame()?;
-
-        let dinfo = DrawTextureParams {
-            dest_size: Some(vec2(NORM_WIDTH_TILE_SIZE,NORM_HEIGHT_TILE_SIZE)),
-            source: Some(r::new(frame.position.x,frame.position.y,frame.size.x,frame.size.y)),
-            rotation: 0.0,
-            flip_x: false,
-            flip_y: true,
-            pivot: None
-        };
-
-        Ok(Self {
+    /// a goomba at its start, without any component
+    pub fn new(sx:f32,sy:f32,direction:Direction,wake_x:Option<f32>) -> Self {
+        Self {
             spos: Vec2::new(sx,sy),
             sdirection: direction,
-            sfreeze: freeze,
-            shape: Rect::new(sx,sy,TILE_SIZE,TILE_SIZE),
-            texture: tex,
-            draw_info: dinfo,
-            spritesheet,
-            velocity: Vec2::new(0.0,0.0),
+            swake: wake_x,
             direction,
-            walk_frame_cnt: 0,
-            die_frame_cnt: 0,
             dead: false,
             disappear: false,
-            freeze,
+            freeze: wake_x.is_some(),
             fall_ctn: 0,
+            knocked: false,
+            knock_velocity: 0.0
+        }
+    }
+
+    /// add a goomba to the world, it wait for the player to reach wake_x before walking
+    pub async fn spawn(world:&mut World,sx:f32,sy:f32,direction:Direction,wake_x:Option<f32>) -> GResult<EntityId> {
+
+        let goomba = Self::new(sx,sy,direction,wake_x);
+
+        let id = world.spawn();
 
-        })
+        world.positions[id] = Some(Vec2::new(sx,sy));
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.colliders[id] = Some(Collider::new(TILE_SIZE,TILE_SIZE,false));
+        world.sprites[id] = Some(Sprite::load("goomba").await?);
+        world.behaviours[id] = Some(Behaviour::Goomba(goomba));
+        // wait for the camera to come close
+        world.active[id] = false;
 
+        Ok(id)
 
     }
 
     pub fn is_dying(&self) -> bool { self.dead }
-    pub fn is_disappear(&self) -> bool { self.disappear }
+    /// the goomba left the level, only a reset bring it back
+    pub fn is_gone(&self) -> bool { self.disappear }
+    pub fn get_start(&self) -> Vec2 { self.spos }
     pub fn unfreeze(&mut self) { self.freeze = false; }
-    pub fn die(&mut self) {
-        if !self.dead {
-            self.dead = true;
-            self.spritesheet.change_current("dead").unwrap();
-            self.reload_sprite();
+    pub fn get_state(&self) -> GoombaState {
+        GoombaState {
+            direction: Facing::from_direction(self.direction),
+            dead: self.dead,
+            disappear: self.disappear,
+            freeze: self.freeze,
+            fall_ctn: self.fall_ctn,
+            knocked: self.knocked,
+            knock_velocity: self.knock_velocity
         }
     }
 
+    pub fn set_state(&mut self,state:&GoombaState) {
+        self.direction = state.direction.get_direction();
+        self.dead = state.dead;
+        self.disappear = state.disappear;
+        self.freeze = state.freeze;
+        self.fall_ctn = state.fall_ctn;
+        self.knocked = state.knocked;
+        self.knock_velocity = state.knock_velocity;
+    }
 
-    fn update_sprite(&mut self) {
-        if self.dead {
-
-            if self.die_frame_cnt > 30 {
-                self.disappear = true;
-            } else {
-                self.die_frame_cnt += 1;
-            }
+    /// make the goomba walk in the given direction
+    pub fn turn_around(&mut self,direction:Direction) { self.direction = direction; }
 
-        } else if self.walk_frame_cnt > 10 {
-            self.spritesheet.increment_current_sprite().unwrap();
-            self.reload_sprite();
+    pub fn die(&mut self,id:EntityId,world:&mut World) {
+        if !self.dead {
+            self.dead = true;
 
-            self.walk_frame_cnt = 0;
+            if let Some(sprite) = world.get_sprite_mut(id) {
+                sprite.play("dead");
+            }
 
-        } else {
-            self.walk_frame_cnt += 1;
+            world.play_sound(SoundEffect::Stomp);
         }
+    }
 
+    /// the goomba flip over, jump and fall off the screen
+    pub fn knock_off(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
 
+        if self.dead {
+            return;
+        }
 
-    }
+        self.dead = true;
+        self.knocked = true;
+        self.knock_velocity = physics.death_velocity;
 
-    pub fn get_rect(&self) -> &Rect { &self.shape }
+        if let Some(collider) = world.colliders[id].as_mut() {
+            collider.ghost = true;
+        }
 
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.set_upside_down(true);
+        }
 
+        world.play_sound(SoundEffect::Stomp);
 
-}
+    }
 
-impl Entity for Goomba {
+    /// remove the goomba from the level without playing its death animation
+    pub fn despawn(&mut self,id:EntityId,world:&mut World) {
+        self.dead = true;
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        self.hide(id,world);
+    }
 
-    fn draw(&self) {
+    fn hide(&mut self,id:EntityId,world:&mut World) {
 
-        let (nx,ny) = get_normalized_position(&self.shape.pos);
+        self.disappear = true;
 
-        draw_texture_ex(
-            self.texture,
-            nx,
-            ny,
-            WHITE,
-            self.draw_info.clone()
-        );
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.visible = false;
+        }
 
     }
 
+    /// the squashed goomba stay on screen until its death clip end
+    pub fn animation_finished(&mut self,id:EntityId,world:&mut World,clip:&str) {
+        if clip == "dead" {
+            self.hide(id,world);
+        }
+    }
 
+    pub fn reset(&mut self,id:EntityId,world:&mut World) {
 
-}
-
-impl Dynamic<&TileMap> for Goomba {
+        world.positions[id] = Some(self.spos);
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.active[id] = false;
 
-    fn reset(&mut self) {
-        self.shape.pos = self.spos;
         self.direction = self.sdirection;
-        self.freeze = self.sfreeze;
+        self.freeze = self.swake.is_some();
 
         self.disappear = false;
         self.dead = false;
-        self.die_frame_cnt = 0;
+        self.knocked = false;
+        self.knock_velocity = 0.0;
 
+        if let Some(collider) = world.colliders[id].as_mut() {
+            collider.ghost = false;
+        }
 
-        self.spritesheet.change_current("walk").unwrap();
-        self.reload_sprite();
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.visible = true;
+            sprite.set_upside_down(false);
+            sprite.play("walk");
+        }
     }
 
-    fn update(&mut self, objects: &TileMap) {
+    /// walk in its direction and fall when there is nothing under it
+    pub fn update(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
+
+        if self.disappear {
+            return;
+        }
+
+        // wait for the player to get close enough
+        if self.freeze {
+
+            let player_x = world.get_player()
+                .and_then(|player| world.get_position(player))
+                .map(|pos| pos.x);
+
+            if let (Some(wake_x),Some(player_x)) = (self.swake,player_x) {
+                if player_x >= wake_x {
+                    self.unfreeze();
+                }
+            }
+
+        }
+
+        if self.knocked {
+            self.fall_off(id,world,physics);
+            return;
+        }
+
+        let (position,velocity) = match (world.positions[id].as_mut(),world.velocities[id].as_mut()) {
+            (Some(position),Some(velocity)) => (position,velocity),
+            _ => return
+        };
 
         if !self.dead && !self.freeze  {
 
             match self.direction {
-                Direction::Left => self.velocity.x = -GVELOCITY,
-                Direction::Right => self.velocity.x = GVELOCITY,
+                Direction::Left => velocity.x = -physics.goomba_velocity,
+                Direction::Right => velocity.x = physics.goomba_velocity,
                 _ => {}
             }
 
-            let mut gravity_velocity = (self.fall_ctn as f32 / 60.0 ) * -5.0;
-            if gravity_velocity < -5.0 {
-                gravity_velocity = -5.0;
+            let mut gravity_velocity = (self.fall_ctn as f32 / 60.0 ) * -physics.enemy_gravity;
+            if gravity_velocity < -physics.terminal_velocity {
+                gravity_velocity = -physics.terminal_velocity;
             }
 
             self.fall_ctn += 1;
-            self.velocity.y += gravity_velocity;
+            velocity.y += gravity_velocity;
 
 
+            if self.direction == Direction::Left && position.x + velocity.x <= 0.0 {
+                self.direction = Direction::Right;
+                velocity.x = physics.goomba_velocity;
+            }
 
-            for tile in objects.iter() {
+        } else {
+            velocity.x = 0.0;
+            velocity.y = 0.0;
+        }
 
-                if tile.is_a_wall() && collision::rect_vs_rect_vertically(
-                    &self.shape,
-                    tile.get_rect(),
-                    self.velocity.y)  {
+    }
 
-                    self.velocity.y = 0.0;
-                    self.shape.pos.y = tile.get_rect().get_y() + TILE_SIZE;
-                    self.fall_ctn = 0;
-                }
+    /// the knocked off goomba jump then fall through the level
+    fn fall_off(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
 
-                if tile.is_a_wall() && collision::rect_vs_rect_horizontally(
-                    &self.shape,
-                    tile.get_rect(),
-                    self.velocity.x) {
+        // far enough under the screen to be forgotten
+        if world.get_position(id).is_some_and(|pos| pos.y < -2.0 * TILE_SIZE) {
+            world.velocities[id] = Some(Vec2::new(0.0,0.0));
+            self.hide(id,world);
+            return;
+        }
 
-                    if self.velocity.x > 0.0 {
+        if let Some(velocity) = world.get_velocity_mut(id) {
 
-                        self.direction = Direction::Left;
-                        self.velocity.x = -GVELOCITY;
+            velocity.x = match self.direction {
+                Direction::Left => -physics.goomba_velocity,
+                _ => physics.goomba_velocity
+            };
+            velocity.y = self.knock_velocity.max(-physics.terminal_velocity);
 
-                    } else {
-                        self.direction = Direction::Right;
-                        self.velocity.x = GVELOCITY;
-                    }
-                }
-            }
+        }
 
+        self.knock_velocity -= physics.death_gravity;
 
-            if self.direction == Direction::Left && self.shape.pos.x + self.velocity.x <= 0.0 {
-                self.direction = Direction::Right;
-                self.velocity.x = GVELOCITY;
-            }
+    }
 
+    pub fn after_move(&mut self,id:EntityId,world:&mut World) {
 
+        if world.get_contacts(id).iter().any(|contact| contact.is_ground()) {
+            self.fall_ctn = 0;
+        }
 
-            self.shape.pos += self.velocity;
+    }
 
+}
 
 
-            if self.shape.get_y() <= 0.0 {
-                self.dead = true;
-                self.disappear = true;
-            }
 
+/// number of frame a brick keep giving coins after its first hit
+const COIN_WINDOW: u32 = 4 * 60;
+
+
+/// fields of a block that change when the player hit it
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct BlockState {
+    pub collected:      bool,
+    /// frames left to take the coins of a brick, started by the first hit
+    pub coin_window:    Option<u32>
+}
+
+
+pub struct MysteryBlocks {
+
+    spos:           Vec2,
+    kind:           BlockKind,
+    collected:      bool,
+    coin_window:    Option<u32>,
+    /// the entity that come out of the block when it is hit
+    item:           Option<EntityId>
+}
+
+impl MysteryBlocks {
+
+    /// a block that wasn't hit yet, without any component nor item
+    pub fn new(x:f32,y:f32,kind:BlockKind) -> Self {
+        Self {
+            spos: Vec2::new(x,y),
+            kind,
+            collected: false,
+            coin_window: None,
+            item: None
         }
+    }
+
+    /// add a block to the world, the other entity can stand on it once it can be seen
+    pub async fn spawn(world:&mut World,x:f32,y:f32,kind:BlockKind) -> GResult<EntityId> {
+
+        // the item exist from the start so the entities of the snapshots never change
+        let item = match kind {
+            BlockKind::Star => Some(Star::spawn(world,x,y).await?),
+            BlockKind::Flower => Some(FireFlower::spawn(world,x,y).await?),
+            _ => None
+        };
+
+        let mut block = Self::new(x,y,kind);
+        block.item = item;
+
+        let id = world.spawn();
+
+        world.positions[id] = Some(Vec2::new(x,y));
+        world.colliders[id] = Some(Collider::new(TILE_SIZE,TILE_SIZE,true));
+        world.sprites[id] = Some(Sprite::load("mblock").await?);
 
+        // the sprite and the collider depend on the kind
+        block.reset(id,world);
+
+        world.behaviours[id] = Some(Behaviour::MysteryBlock(block));
+
+        Ok(id)
 
-        self.update_sprite();
     }
 
-}
+    pub fn reset(&mut self,id:EntityId,world:&mut World) {
 
+        self.collected = false;
+        self.coin_window = None;
 
+        let hidden = self.kind == BlockKind::Hidden;
 
-impl SpriteUser for Goomba {
-    fn reload_sprite(&mut self) {
+        if let Some(collider) = world.colliders[id].as_mut() {
+            collider.from_below = hidden;
+        }
 
-        let frame = self.spritesheet.get_current_frame().unwrap();
-        self.draw_info.flip_x = self.spritesheet.should_flip();
-        self.draw_info.source = Some(r::new(
-            frame.position.x,
-            frame.position.y,
-            frame.size.x,
-            frame.size.y
-        ));
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.visible = !hidden;
+            sprite.play(match self.kind {
+                BlockKind::Mystery | BlockKind::Star | BlockKind::Flower => "normal",
+                BlockKind::Hidden => "collected",
+                BlockKind::Coins => "brick"
+            });
+        }
 
     }
 
-}
+    /// the time to take the coins of a brick run out
+    pub fn update(&mut self) {
+        if let Some(window) = self.coin_window.as_mut() {
+            *window = window.saturating_sub(1);
+        }
+    }
 
+    /// give a coin when it is hit, a brick give one on each hit until its time run out, an empty block only bump.
+    /// Return true when a coin was given
+    pub fn collect(&mut self,id:EntityId,world:&mut World) -> bool {
 
-pub struct MysteryBlocks {
+        if self.collected {
+            world.play_sound(SoundEffect::Bump);
+            return false;
+        }
 
-    shape:          Rect,
-    texture:        Texture2D,
-    draw_info:      DrawTextureParams,
-    spritesheet:    SpriteSheet,
-    collected:      bool,
-    sprite_update:  u8
-}
+        if let Some(item) = self.item {
 
-impl MysteryBlocks {
+            self.empty(id,world);
+
+            world.with_behaviour(item,|behaviour,world| behaviour.release(item,world));
+
+            world.play_sound(SoundEffect::Bump);
+
+            return false;
+
+        }
+
+        world.play_sound(SoundEffect::Coin);
+
+        match self.kind {
+            BlockKind::Mystery | BlockKind::Star | BlockKind::Flower => self.empty(id,world),
+            BlockKind::Hidden => {
+
+                // found, it stay solid from every side
+                if let Some(collider) = world.colliders[id].as_mut() {
+                    collider.from_below = false;
+                }
+
+                if let Some(sprite) = world.get_sprite_mut(id) {
+                    sprite.visible = true;
+                }
 
-    pub async fn new(x:f32,y:f32) -> GResult<Self> {
+                self.empty(id,world);
 
-        let p = format!("{}/mblock.png",ASSETS_DIR);
+            },
+            BlockKind::Coins => {
+
+                // the hit after the end of the time still give the last coin
+                if *self.coin_window.get_or_insert(COIN_WINDOW) == 0 {
+                    self.empty(id,world);
+                }
+
+            }
+        }
+
+        true
+
+    }
+
+    fn empty(&mut self,id:EntityId,world:&mut World) {
+
+        self.collected = true;
+
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.play("collected");
+        }
+
+    }
+
+    pub fn get_state(&self) -> BlockState {
+        BlockState {
+            collected: self.collected,
+            coin_window: self.coin_window
+        }
+    }
 
-        let tex = load_texture(&p).await?;
+    pub fn set_state(&mut self,state:&BlockState) {
+        self.collected = state.collected;
+        self.coin_window = state.coin_window;
+    }
+
+    pub fn get_start(&self) -> Vec2 { self.spos }
 
-        let mut spritesheet = SpriteSheet::new(TILE_SIZE,TILE_SIZE);
+}
 
-        spritesheet.add_sprite("normal",3,0.0,0.0,0.0)?;
-        spritesheet.add_sprite("collected",1,48.0,0.0,0.0)?;
 
-        // default frame
-        let frame = spritesheet.get_current_frame()?;
 
-        let dinfo = DrawTextureParams {
-            dest_size: Some(vec2(NORM_WIDTH_TILE_SIZE,NORM_HEIGHT_TILE_SIZE)),
-            source: Some(r::new(frame.position.x,frame.position.y,frame.size.x,frame.size.y)),
-            rotation: 0.0,
-            flip_x: false,
-            flip_y: true,
-            pivot: None
+/// spin in the air until the player touch it
+pub struct Coin {
+    spos:       Vec2,
+    collected:  bool
+}
+
+impl Coin {
+
+    /// add a coin to the world, it doesn't stop anything
+    pub async fn spawn(world:&mut World,x:f32,y:f32) -> GResult<EntityId> {
+
+        let coin = Self {
+            spos: Vec2::new(x,y),
+            collected: false
         };
 
-        Ok(Self{
-            shape: Rect::new(x,y,TILE_SIZE,TILE_SIZE),
-            texture: tex,
-            spritesheet,
-            draw_info: dinfo,
-            collected: false,
-            sprite_update: 0
+        let id = world.spawn();
 
-        })
+        world.positions[id] = Some(Vec2::new(x,y));
+        world.colliders[id] = Some(Collider::new(TILE_SIZE,TILE_SIZE,false));
+        world.sprites[id] = Some(Sprite::load("coin").await?);
+        world.behaviours[id] = Some(Behaviour::Coin(coin));
+
+        Ok(id)
 
     }
 
-    pub fn update(&mut self) {
-        self.update_sprite();
+    pub fn reset(&mut self,id:EntityId,world:&mut World) {
+
+        self.collected = false;
+
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.visible = true;
+        }
+
     }
 
-    pub fn reset(&mut self) {
+    pub fn collect(&mut self,id:EntityId,world:&mut World) {
 
         if self.collected {
-            self.collected = false;
-            self.spritesheet.change_current("normal").unwrap();
-            self.reload_sprite();
+            return;
+        }
+
+        self.collected = true;
+
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.visible = false;
         }
 
+        world.play_sound(SoundEffect::Coin);
 
     }
 
-    pub fn get_rect(&self) -> &Rect { &self.shape }
+    pub fn is_collected(&self) -> bool { self.collected }
+    pub fn set_collected(&mut self,collected:bool) { self.collected = collected; }
+
+    pub fn get_start(&self) -> Vec2 { self.spos }
+
+}
+
+
+
+/// number of frame an item take to rise out of its block
+const ITEM_EMERGE: u32 = 32;
+/// horizontal speed of the star once it is out
+const STAR_SPEED: f32 = 1.5;
+/// vertical speed of the star each time it touch the ground
+const STAR_BOUNCE: f32 = 4.0;
+/// velocity the star lose each frame while it is in the air
+const STAR_GRAVITY: f32 = 0.2;
+
+
+/// every field of a star that change while it bounce
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct StarState {
+    pub emerge_ctn: u32,
+    pub yvelocity:  f32,
+    pub direction:  Facing,
+    pub collected:  bool
+}
+
+
+/// wait in its block until it is hit, then bounce through the level until the player take it
+pub struct Star {
+    spos:       Vec2,
+    /// frames left before it is out of its block
+    emerge_ctn: u32,
+    yvelocity:  f32,
+    direction:  Direction,
+    collected:  bool
+}
+
+impl Star {
+
+    /// add a star hidden in the block at the given position
+    pub async fn spawn(world:&mut World,x:f32,y:f32) -> GResult<EntityId> {
+
+        let mut star = Self {
+            spos: Vec2::new(x,y),
+            emerge_ctn: 0,
+            yvelocity: 0.0,
+            direction: Direction::Right,
+            collected: false
+        };
+
+        let id = world.spawn();
 
+        world.positions[id] = Some(Vec2::new(x,y));
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.colliders[id] = Some(Collider::new(TILE_SIZE,TILE_SIZE,false));
+        world.sprites[id] = Some(Sprite::load("star").await?);
 
-    pub fn collect(&mut self) {
-        if !self.collected {
-            self.collected = true;
+        star.reset(id,world);
 
-            self.spritesheet.change_current("collected").unwrap();
-            self.reload_sprite();
+        world.behaviours[id] = Some(Behaviour::Star(star));
 
+        Ok(id)
+
+    }
+
+    pub fn reset(&mut self,id:EntityId,world:&mut World) {
+
+        world.positions[id] = Some(self.spos);
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        // it is only updated once its block is hit
+        world.active[id] = false;
+
+        self.emerge_ctn = 0;
+        self.yvelocity = 0.0;
+        self.direction = Direction::Right;
+        self.collected = false;
+
+        if let Some(collider) = world.colliders[id].as_mut() {
+            collider.ghost = false;
+        }
+
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.visible = true;
         }
 
     }
 
-    fn update_sprite(&mut self) {
+    /// the block was hit, the star rise out of it through the block
+    pub fn release(&mut self,id:EntityId,world:&mut World) {
+
+        world.active[id] = true;
+        self.emerge_ctn = ITEM_EMERGE;
+
+        if let Some(collider) = world.colliders[id].as_mut() {
+            collider.ghost = true;
+        }
+
+    }
+
+    pub fn update(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
+
+        if self.emerge_ctn > 0 {
+
+            self.emerge_ctn -= 1;
+
+            if let Some(velocity) = world.get_velocity_mut(id) {
+                *velocity = Vec2::new(0.0,TILE_SIZE / ITEM_EMERGE as f32);
+            }
 
-        if !self.collected {
+            // out of the block, it start to bounce
+            if self.emerge_ctn == 0 {
 
-            if self.sprite_update > 15 {
-                self.spritesheet.increment_current_sprite().unwrap();
+                self.yvelocity = STAR_BOUNCE;
 
-                self.reload_sprite();
+                if let Some(collider) = world.colliders[id].as_mut() {
+                    collider.ghost = false;
+                }
 
-                self.sprite_update = 0;
-            } else {
-                self.sprite_update += 1;
             }
 
+            return;
 
         }
 
+        // fell in a pit
+        if world.get_position(id).is_some_and(|pos| pos.y < -TILE_SIZE) {
+            world.velocities[id] = Some(Vec2::new(0.0,0.0));
+            world.active[id] = false;
+            return;
+        }
+
+        self.yvelocity = (self.yvelocity - STAR_GRAVITY).max(-physics.terminal_velocity);
+
+        if let Some(velocity) = world.get_velocity_mut(id) {
+
+            velocity.x = match self.direction {
+                Direction::Left => -STAR_SPEED,
+                _ => STAR_SPEED
+            };
+            velocity.y = self.yvelocity;
+
+        }
 
     }
 
-}
+    /// bounce on the ground, stop rising under a ceiling and turn back on a wall
+    pub fn after_move(&mut self,id:EntityId,world:&mut World) {
 
-impl Entity for MysteryBlocks {
+        for contact in world.get_contacts(id).iter() {
 
-    fn draw(&self) {
-        let (nx,ny) = get_normalized_position(&self.shape.pos);
+            if contact.is_ground() {
+                self.yvelocity = STAR_BOUNCE;
+            } else if contact.is_ceiling() {
+                self.yvelocity = self.yvelocity.min(0.0);
+            } else if contact.is_wall() {
+                self.direction = if contact.normal.x < 0.0 { Direction::Left } else { Direction::Right };
+            }
 
-        draw_texture_ex(
-            self.texture,
-            nx,
-            ny,
-            WHITE,
-            self.draw_info.clone()
-        );
+        }
 
     }
 
+    /// the player took the star
+    pub fn collect(&mut self,id:EntityId,world:&mut World) {
 
+        self.collected = true;
 
-}
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.active[id] = false;
 
-impl Dynamic<()> for MysteryBlocks {
-    fn reset(&mut self) {
-        if self.collected {
-            self.collected = false;
-            self.spritesheet.change_current("normal").unwrap();
-            self.reload_sprite();
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.visible = false;
         }
+
     }
 
-    fn update(&mut self, _entity: ()) { self.update_sprite(); }
+    pub fn is_collected(&self) -> bool { self.collected }
+
+    pub fn get_state(&self) -> StarState {
+        StarState {
+            emerge_ctn: self.emerge_ctn,
+            yvelocity: self.yvelocity,
+            direction: Facing::from_direction(self.direction),
+            collected: self.collected
+        }
+    }
+
+    pub fn set_state(&mut self,state:&StarState) {
+        self.emerge_ctn = state.emerge_ctn;
+        self.yvelocity = state.yvelocity;
+        self.direction = state.direction.get_direction();
+        self.collected = state.collected;
+    }
+
+    pub fn get_start(&self) -> Vec2 { self.spos }
+
+}
+
+
 
+/// every field of a fire flower that change when it is found
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct FlowerState {
+    pub emerge_ctn: u32,
+    pub collected:  bool
 }
 
 
-impl SpriteUser for MysteryBlocks {
-    fn reload_sprite(&mut self) {
-        let frame = self.spritesheet.get_current_frame().unwrap();
-        self.draw_info.flip_x = self.spritesheet.should_flip();
-        self.draw_info.source = Some(r::new(
-            frame.position.x,
-            frame.position.y,
-            frame.size.x,
-            frame.size.y
-        ));
+/// wait in its block until it is hit, then stay on top of it until the player take it
+pub struct FireFlower {
+    spos:       Vec2,
+    /// frames left before it is out of its block
+    emerge_ctn: u32,
+    collected:  bool
+}
+
+impl FireFlower {
+
+    /// add a fire flower hidden in the block at the given position
+    pub async fn spawn(world:&mut World,x:f32,y:f32) -> GResult<EntityId> {
+
+        let mut flower = Self {
+            spos: Vec2::new(x,y),
+            emerge_ctn: 0,
+            collected: false
+        };
+
+        let id = world.spawn();
+
+        // it never fall, it only rise through its block
+        let mut collider = Collider::new(TILE_SIZE,TILE_SIZE,false);
+        collider.ghost = true;
+
+        world.positions[id] = Some(Vec2::new(x,y));
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.colliders[id] = Some(collider);
+        world.sprites[id] = Some(Sprite::load("flower").await?);
+
+        flower.reset(id,world);
+
+        world.behaviours[id] = Some(Behaviour::FireFlower(flower));
+
+        Ok(id)
+
+    }
+
+    pub fn reset(&mut self,id:EntityId,world:&mut World) {
+
+        world.positions[id] = Some(self.spos);
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        // it is only updated once its block is hit
+        world.active[id] = false;
+
+        self.emerge_ctn = 0;
+        self.collected = false;
+
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.visible = true;
+        }
+
     }
 
+    /// the block was hit, the flower rise out of it
+    pub fn release(&mut self,id:EntityId,world:&mut World) {
+        world.active[id] = true;
+        self.emerge_ctn = ITEM_EMERGE;
+    }
+
+    pub fn update(&mut self,id:EntityId,world:&mut World) {
+
+        let rise = if self.emerge_ctn > 0 { TILE_SIZE / ITEM_EMERGE as f32 } else { 0.0 };
+        self.emerge_ctn = self.emerge_ctn.saturating_sub(1);
+
+        if let Some(velocity) = world.get_velocity_mut(id) {
+            *velocity = Vec2::new(0.0,rise);
+        }
+
+    }
+
+    /// the player took the flower
+    pub fn collect(&mut self,id:EntityId,world:&mut World) {
+
+        self.collected = true;
+
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.active[id] = false;
+
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.visible = false;
+        }
+
+    }
+
+    pub fn is_collected(&self) -> bool { self.collected }
+
+    pub fn get_state(&self) -> FlowerState {
+        FlowerState {
+            emerge_ctn: self.emerge_ctn,
+            collected: self.collected
+        }
+    }
+
+    pub fn set_state(&mut self,state:&FlowerState) {
+        self.emerge_ctn = state.emerge_ctn;
+        self.collected = state.collected;
+    }
+
+    pub fn get_start(&self) -> Vec2 { self.spos }
+
 }
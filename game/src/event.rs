@@ -0,0 +1,309 @@
+
+use crate::collision::SolidKind;
+use crate::world::{Behaviour, EntityId, World};
+
+use lib_game::collision;
+use lib_game::shape::{Rect, Shape};
+use lib_game::Direction;
+
+
+/// something that happened during the collision pass
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ContactEvent {
+    /// the player landed on top of an enemy
+    PlayerStompedEnemy(EntityId),
+    /// an enemy touched the player anywhere else than on its head
+    EnemyHitPlayer(EntityId),
+    /// the player jumped in the bottom of a mystery block
+    PlayerHitBlockFromBelow(EntityId),
+    /// the player touched an item he pick up, like a coin
+    PlayerCollected(EntityId),
+    /// a fireball touched an enemy, the fireball then the enemy
+    FireballHitEnemy(EntityId,EntityId),
+    /// an enemy walked into a wall, the direction is where the wall face
+    EnemyHitWall(EntityId,Direction),
+    /// the entity got under the bottom of the level
+    FellOutOfWorld(EntityId)
+}
+
+
+fn is_mystery_block(world:&World,id:EntityId) -> bool {
+    matches!(world.get_behaviour(id),Some(Behaviour::MysteryBlock(_)))
+}
+
+/// an enemy that didn't spawn yet can't touch anything
+fn is_enemy(world:&World,id:EntityId) -> bool {
+    world.is_active(id) && world.get_behaviour(id).is_some_and(|behaviour| behaviour.is_enemy())
+}
+
+fn is_projectile(world:&World,id:EntityId) -> bool {
+    world.is_active(id) && world.get_behaviour(id).is_some_and(|behaviour| behaviour.is_projectile())
+}
+
+
+/// look at what every entity touched while moving this frame and report it as a list of event
+pub fn collect_contact_events(world:&World) -> Vec<ContactEvent> {
+
+    let mut events = Vec::new();
+
+    if let Some(player) = world.get_player() {
+
+        let dying = world.get_behaviour(player)
+            .and_then(|behaviour| behaviour.as_player())
+            .map(|player| player.is_dying())
+            .unwrap_or(true);
+
+        let star = world.get_behaviour(player)
+            .and_then(|behaviour| behaviour.as_player())
+            .is_some_and(|player| player.has_star());
+
+        if let (false,Some(rect)) = (dying,world.get_rect(player)) {
+
+            for contact in world.get_contacts(player).iter() {
+
+                if let SolidKind::Entity(id) = contact.kind {
+                    if contact.is_ceiling() && is_mystery_block(world,id) {
+                        events.push(ContactEvent::PlayerHitBlockFromBelow(id));
+                    }
+                }
+
+            }
+
+            for id in world.entities().filter(|id| world.is_active(*id)) {
+
+                let collectible = world.get_behaviour(id).is_some_and(|behaviour| behaviour.is_collectible());
+
+                if collectible && world.get_rect(id).is_some_and(|item| collision::rect_vs_rect(&rect,&item)) {
+                    events.push(ContactEvent::PlayerCollected(id));
+                }
+
+            }
+
+            // how much the player moved vertically during this frame
+            let previous = world.get_previous(player);
+            let yvelocity = rect.pos.y - previous.y;
+            let previous = Rect::new(previous.x,previous.y,rect.get_width(),rect.get_height());
+
+            for id in world.entities().filter(|id| is_enemy(world,*id)) {
+
+                let enemy = match world.get_rect(id) {
+                    Some(enemy) => enemy,
+                    None => continue
+                };
+
+                if !collision::rect_vs_rect(&rect,&enemy) {
+                    continue;
+                }
+
+                if collision::rect_vs_rect_vertically(&previous,&enemy,yvelocity) && yvelocity < 0.0 {
+                    events.push(ContactEvent::PlayerStompedEnemy(id));
+                } else {
+                    events.push(ContactEvent::EnemyHitPlayer(id));
+
+                    // the player can only be killed once, with the star every enemy he touch is knocked off
+                    if !star {
+                        break;
+                    }
+                }
+
+            }
+
+        }
+
+    }
+
+    // a fireball burn the first enemy it touch
+    for id in world.entities().filter(|id| is_projectile(world,*id)) {
+
+        let rect = match world.get_rect(id) {
+            Some(rect) => rect,
+            None => continue
+        };
+
+        let enemy = world.entities()
+            .filter(|enemy| is_enemy(world,*enemy))
+            .find(|enemy| world.get_rect(*enemy).is_some_and(|enemy| collision::rect_vs_rect(&rect,&enemy)));
+
+        if let Some(enemy) = enemy {
+            events.push(ContactEvent::FireballHitEnemy(id,enemy));
+        }
+
+    }
+
+    for id in world.entities() {
+
+        // only the entity that move can leave the level
+        if world.get_velocity(id).is_none() {
+            continue;
+        }
+
+        if is_enemy(world,id) {
+
+            for contact in world.get_contacts(id).iter() {
+
+                if contact.is_wall() {
+
+                    let side = if contact.normal.x < 0.0 { Direction::Left } else { Direction::Right };
+                    events.push(ContactEvent::EnemyHitWall(id,side));
+
+                }
+
+            }
+
+        }
+
+        // the dying player is still checked, his death animation finish when he leave the screen
+        let tracked = Some(id) == world.get_player() || is_enemy(world,id);
+        let ghost = world.colliders[id].is_some_and(|collider| collider.ghost);
+
+        if tracked && !ghost && world.get_position(id).is_some_and(|pos| pos.y <= 0.0) {
+            events.push(ContactEvent::FellOutOfWorld(id));
+        }
+
+    }
+
+    events
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::collision::Contact;
+    use crate::component::Collider;
+    use crate::entity::{Goomba, MysteryBlocks};
+    use crate::level::BlockKind;
+    use crate::physics::PhysicsProfile;
+    use crate::player::Player;
+
+    use lib_game::vector::Vec2;
+
+    const TILE: f32 = 16.0;
+
+    /// a tile sized entity, without sprite, at the given position
+    fn add_entity(world:&mut World,x:f32,y:f32,behaviour:Behaviour) -> EntityId {
+
+        let id = world.spawn();
+
+        world.positions[id] = Some(Vec2::new(x,y));
+        world.previous[id] = Vec2::new(x,y);
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.colliders[id] = Some(Collider::new(TILE,TILE,false));
+        world.behaviours[id] = Some(behaviour);
+
+        id
+
+    }
+
+    /// the player moved from the previous position to the current one during the frame
+    fn add_player(world:&mut World,previous:(f32,f32),current:(f32,f32)) -> EntityId {
+
+        let player = Player::new(Vec2::new(previous.0,previous.1),&PhysicsProfile::default());
+        let id = add_entity(world,current.0,current.1,Behaviour::Player(player));
+
+        world.previous[id] = Vec2::new(previous.0,previous.1);
+        world.set_player(id);
+
+        id
+
+    }
+
+    fn add_goomba(world:&mut World,x:f32,y:f32) -> EntityId {
+        add_entity(world,x,y,Behaviour::Goomba(Goomba::new(x,y,Direction::Left,None)))
+    }
+
+    #[test]
+    fn falling_on_an_enemy_stomp_it() {
+
+        let mut world = World::new();
+        add_player(&mut world,(100.0,36.0),(100.0,30.0));
+        let goomba = add_goomba(&mut world,100.0,16.0);
+
+        assert_eq!(collect_contact_events(&world),vec![ContactEvent::PlayerStompedEnemy(goomba)]);
+
+    }
+
+    #[test]
+    fn rising_into_an_enemy_hurt_the_player() {
+
+        // the same overlap as a stomp, but the player go up
+        let mut world = World::new();
+        add_player(&mut world,(100.0,24.0),(100.0,30.0));
+        let goomba = add_goomba(&mut world,100.0,16.0);
+
+        assert_eq!(collect_contact_events(&world),vec![ContactEvent::EnemyHitPlayer(goomba)]);
+
+    }
+
+    #[test]
+    fn walking_into_an_enemy_hurt_the_player() {
+
+        let mut world = World::new();
+        add_player(&mut world,(82.0,16.0),(86.0,16.0));
+        let goomba = add_goomba(&mut world,100.0,16.0);
+
+        assert_eq!(collect_contact_events(&world),vec![ContactEvent::EnemyHitPlayer(goomba)]);
+
+    }
+
+    #[test]
+    fn head_on_a_block_ceiling_hit_it_from_below() {
+
+        let mut world = World::new();
+        let player = add_player(&mut world,(100.0,40.0),(100.0,44.0));
+        let block = add_entity(&mut world,100.0,60.0,Behaviour::MysteryBlock(MysteryBlocks::new(100.0,60.0,BlockKind::Mystery)));
+
+        world.contacts[player].push(Contact { kind: SolidKind::Entity(block), normal: Vec2::new(0.0,-1.0) });
+
+        assert_eq!(collect_contact_events(&world),vec![ContactEvent::PlayerHitBlockFromBelow(block)]);
+
+    }
+
+    #[test]
+    fn enemy_wall_give_the_side_the_wall_face() {
+
+        let mut world = World::new();
+        let left = add_goomba(&mut world,100.0,16.0);
+        let right = add_goomba(&mut world,200.0,16.0);
+
+        // a wall on the right push back to the left, and the other way
+        world.contacts[left].push(Contact { kind: SolidKind::Tile, normal: Vec2::new(-1.0,0.0) });
+        world.contacts[right].push(Contact { kind: SolidKind::Tile, normal: Vec2::new(1.0,0.0) });
+
+        assert_eq!(collect_contact_events(&world),vec![
+            ContactEvent::EnemyHitWall(left,Direction::Left),
+            ContactEvent::EnemyHitWall(right,Direction::Right)
+        ]);
+
+    }
+
+    #[test]
+    fn falling_under_the_level_is_reported_except_for_ghosts() {
+
+        let mut world = World::new();
+        let player = add_player(&mut world,(100.0,2.0),(100.0,-4.0));
+        let goomba = add_goomba(&mut world,300.0,-4.0);
+        let ghost = add_goomba(&mut world,500.0,-4.0);
+
+        if let Some(collider) = world.colliders[ghost].as_mut() {
+            collider.ghost = true;
+        }
+
+        assert_eq!(collect_contact_events(&world),vec![
+            ContactEvent::FellOutOfWorld(player),
+            ContactEvent::FellOutOfWorld(goomba)
+        ]);
+
+        // a player flying with noclip can go under the level
+        if let Some(collider) = world.colliders[player].as_mut() {
+            collider.ghost = true;
+        }
+
+        assert_eq!(collect_contact_events(&world),vec![ContactEvent::FellOutOfWorld(goomba)]);
+
+    }
+
+}
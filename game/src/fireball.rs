@@ -0,0 +1,220 @@
+
+use crate::audio::SoundEffect;
+use crate::component::{Collider, Sprite};
+use crate::declaration::TILE_SIZE;
+use crate::level::Facing;
+use crate::physics::PhysicsProfile;
+use crate::world::{Behaviour, EntityId, World};
+
+use lib_game::GResult;
+use lib_game::shape::{Rect, Shape};
+use lib_game::vector::Vec2;
+use lib_game::Direction;
+
+use serde::{Deserialize, Serialize};
+
+
+/// number of fireball the player can have on screen at once
+pub const MAX_FIREBALLS: usize = 2;
+/// width and height of the hitbox and the sprite
+const FIREBALL_SIZE: f32 = TILE_SIZE / 2.0;
+/// horizontal speed of a fireball
+const FIREBALL_SPEED: f32 = 4.0;
+/// vertical speed of a fireball each time it touch the floor
+const FIREBALL_BOUNCE: f32 = 3.0;
+/// velocity a fireball lose each frame while it is in the air
+const FIREBALL_GRAVITY: f32 = 0.4;
+/// vertical speed of a fireball when it leave the hand of the player
+const FIREBALL_THROW: f32 = -2.0;
+
+
+/// every field of a fireball that change while it fly
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct FireballState {
+    pub direction:  Facing,
+    pub yvelocity:  f32,
+    pub exploding:  bool
+}
+
+
+/// thrown by the player, it bounce along the floor until it hit a wall or an enemy
+pub struct Fireball {
+    direction:  Direction,
+    yvelocity:  f32,
+    /// the explosion play, it doesn't move nor burn anything anymore
+    exploding:  bool
+}
+
+impl Fireball {
+
+    /// add a fireball waiting to be thrown, the same ones are thrown again and again
+    pub async fn spawn(world:&mut World) -> GResult<EntityId> {
+
+        let mut fireball = Self {
+            direction: Direction::Right,
+            yvelocity: 0.0,
+            exploding: false
+        };
+
+        let mut sprite = Sprite::load("fireball").await?;
+        sprite.set_size(FIREBALL_SIZE,FIREBALL_SIZE);
+
+        let id = world.spawn();
+
+        world.positions[id] = Some(Vec2::new(0.0,0.0));
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.colliders[id] = Some(Collider::new(FIREBALL_SIZE,FIREBALL_SIZE,false));
+        world.sprites[id] = Some(sprite);
+
+        fireball.reset(id,world);
+
+        world.behaviours[id] = Some(Behaviour::Fireball(fireball));
+
+        Ok(id)
+
+    }
+
+    pub fn reset(&mut self,id:EntityId,world:&mut World) {
+        self.despawn(id,world);
+    }
+
+    /// a fireball has no place in the level, it is only somewhere once thrown
+    pub fn get_start(&self) -> Vec2 { Vec2::new(0.0,0.0) }
+
+    pub fn is_exploding(&self) -> bool { self.exploding }
+
+    pub fn get_state(&self) -> FireballState {
+        FireballState {
+            direction: Facing::from_direction(self.direction),
+            yvelocity: self.yvelocity,
+            exploding: self.exploding
+        }
+    }
+
+    pub fn set_state(&mut self,state:&FireballState) {
+        self.direction = state.direction.get_direction();
+        self.yvelocity = state.yvelocity;
+        self.exploding = state.exploding;
+    }
+
+    /// leave the hand of the thrower, in front of the top of its hitbox
+    pub fn throw(&mut self,id:EntityId,world:&mut World,thrower:&Rect,direction:Direction) {
+
+        let x = match direction {
+            Direction::Left => thrower.pos.x - FIREBALL_SIZE,
+            _ => thrower.get_max_x()
+        };
+        let y = thrower.pos.y + thrower.get_height() - TILE_SIZE;
+
+        world.positions[id] = Some(Vec2::new(x,y));
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.active[id] = true;
+
+        self.direction = direction;
+        self.yvelocity = FIREBALL_THROW;
+        self.exploding = false;
+
+        if let Some(collider) = world.colliders[id].as_mut() {
+            collider.ghost = false;
+        }
+
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.visible = true;
+            sprite.play("spin");
+        }
+
+        world.play_sound(SoundEffect::Fireball);
+
+    }
+
+    pub fn update(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
+
+        if self.exploding {
+            world.velocities[id] = Some(Vec2::new(0.0,0.0));
+            return;
+        }
+
+        // fell in a pit
+        if world.get_position(id).is_some_and(|pos| pos.y < -TILE_SIZE) {
+            self.despawn(id,world);
+            return;
+        }
+
+        self.yvelocity = (self.yvelocity - FIREBALL_GRAVITY).max(-physics.terminal_velocity);
+
+        if let Some(velocity) = world.get_velocity_mut(id) {
+
+            velocity.x = match self.direction {
+                Direction::Left => -FIREBALL_SPEED,
+                _ => FIREBALL_SPEED
+            };
+            velocity.y = self.yvelocity;
+
+        }
+
+    }
+
+    /// bounce on the floor and explode against a wall
+    pub fn after_move(&mut self,id:EntityId,world:&mut World) {
+
+        let contacts = world.get_contacts(id);
+
+        let wall = contacts.iter().any(|contact| contact.is_wall());
+        let ground = contacts.iter().any(|contact| contact.is_ground());
+        let ceiling = contacts.iter().any(|contact| contact.is_ceiling());
+
+        if wall {
+            self.explode(id,world);
+        } else if ground {
+            self.yvelocity = FIREBALL_BOUNCE;
+        } else if ceiling {
+            self.yvelocity = self.yvelocity.min(0.0);
+        }
+
+    }
+
+    /// stop where it is and play the explosion, it is gone once the clip end
+    pub fn explode(&mut self,id:EntityId,world:&mut World) {
+
+        if self.exploding {
+            return;
+        }
+
+        self.exploding = true;
+
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+
+        if let Some(collider) = world.colliders[id].as_mut() {
+            collider.ghost = true;
+        }
+
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.play("explode");
+        }
+
+        world.play_sound(SoundEffect::Bump);
+
+    }
+
+    pub fn animation_finished(&mut self,id:EntityId,world:&mut World,clip:&str) {
+        if clip == "explode" {
+            self.despawn(id,world);
+        }
+    }
+
+    /// the fireball is ready to be thrown again
+    pub fn despawn(&mut self,id:EntityId,world:&mut World) {
+
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.active[id] = false;
+
+        self.yvelocity = 0.0;
+        self.exploding = false;
+
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.visible = false;
+        }
+
+    }
+
+}
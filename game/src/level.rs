@@ -0,0 +1,430 @@
+
+use crate::declaration::TILE_SIZE;
+use crate::tiled::{Properties, TiledMap};
+
+use lib_game::GResult;
+use lib_game::Direction;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+
+
+/// which way a goomba start walking
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Facing {
+    Left,
+    Right
+}
+
+impl Facing {
+
+    fn from_name(name:&str) -> Option<Self> {
+        match name {
+            "left" => Some(Facing::Left),
+            "right" => Some(Facing::Right),
+            _ => None
+        }
+    }
+
+    /// the vertical directions have no facing, they are taken as right
+    pub fn from_direction(direction:Direction) -> Self {
+        match direction {
+            Direction::Left => Facing::Left,
+            _ => Facing::Right
+        }
+    }
+
+    pub fn get_direction(&self) -> Direction {
+        match self {
+            Facing::Left => Direction::Left,
+            Facing::Right => Direction::Right
+        }
+    }
+
+}
+
+
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct Point {
+    pub x:  f32,
+    pub y:  f32
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct GoombaData {
+    pub x:          f32,
+    pub y:          f32,
+    pub direction:  Facing,
+    /// x position the player must reach before the goomba start walking
+    #[serde(default,skip_serializing_if = "Option::is_none")]
+    pub wake_x:     Option<f32>
+}
+
+/// what a block give when the player hit it from below
+#[derive(Debug,Clone,Copy,PartialEq,Default,Deserialize,Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockKind {
+    /// one coin, then it is empty
+    #[default]
+    Mystery,
+    /// invisible and only solid from below until the player find it
+    Hidden,
+    /// a brick that give a coin on each hit for a while, then it is empty
+    Coins,
+    /// a mystery block with a star in it
+    Star,
+    /// a mystery block with a fire flower in it
+    Flower
+}
+
+impl BlockKind {
+
+    pub fn from_name(name:&str) -> Option<Self> {
+        match name {
+            "mystery" => Some(BlockKind::Mystery),
+            "hidden" => Some(BlockKind::Hidden),
+            "coins" => Some(BlockKind::Coins),
+            "star" => Some(BlockKind::Star),
+            "flower" => Some(BlockKind::Flower),
+            _ => None
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            BlockKind::Mystery => "mystery",
+            BlockKind::Hidden => "hidden",
+            BlockKind::Coins => "coins",
+            BlockKind::Star => "star",
+            BlockKind::Flower => "flower"
+        }
+    }
+
+    fn is_mystery(&self) -> bool { *self == BlockKind::Mystery }
+
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct BlockData {
+    pub x:      f32,
+    pub y:      f32,
+    #[serde(default,skip_serializing_if = "BlockKind::is_mystery")]
+    pub kind:   BlockKind
+}
+
+/// path followed by a platform, the distances are in pixel and the speeds in pixel by frame
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+#[serde(tag = "type",rename_all = "lowercase")]
+pub enum PlatformMotion {
+    /// go right and come back, a negative distance go left first
+    Horizontal { distance: f32, speed: f32 },
+    /// go up and come back, a negative distance go down first
+    Vertical { distance: f32, speed: f32 },
+    /// go round a circle that pass by the start position, a negative speed turn the other way
+    Loop { radius: f32, speed: f32 },
+    /// keep going up, or down with a negative distance, and start again from the start after the distance
+    Elevator { distance: f32, speed: f32 },
+    /// stay still until the player stand on it, then fall
+    Falling
+}
+
+impl PlatformMotion {
+
+    /// motion of a platform object of a tiled map, from its custom properties
+    fn from_properties(name:&str,properties:&Properties) -> GResult<Self> {
+
+        let distance = properties.get_f32("distance").unwrap_or(3.0 * TILE_SIZE);
+        let speed = properties.get_f32("speed").unwrap_or(1.0);
+
+        let motion = match properties.get_str("motion").unwrap_or("horizontal") {
+            "horizontal" => PlatformMotion::Horizontal { distance, speed },
+            "vertical" => PlatformMotion::Vertical { distance, speed },
+            "loop" => PlatformMotion::Loop { radius: properties.get_f32("radius").unwrap_or(2.0 * TILE_SIZE), speed },
+            "elevator" => PlatformMotion::Elevator { distance, speed },
+            "falling" => PlatformMotion::Falling,
+            motion => bail!("platform '{}' has an invalid motion '{}'",name,motion)
+        };
+
+        Ok(motion)
+
+    }
+
+}
+
+fn default_platform_width() -> u32 { 3 }
+
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct PlatformData {
+    pub x:      f32,
+    pub y:      f32,
+    /// number of tile
+    #[serde(default = "default_platform_width")]
+    pub width:  u32,
+    pub motion: PlatformMotion
+}
+
+/// once the player pass x, he come back to the checkpoint position after a death
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct Checkpoint {
+    pub x:  f32,
+    pub y:  f32
+}
+
+
+/// what happen to an entity when the player respawn at a checkpoint
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RestoreRule {
+    /// put back as it was when the level started
+    Reset,
+    /// stay as it was when the player died
+    Keep,
+    /// reset only if it start after the checkpoint, keep it otherwise
+    Ahead
+}
+
+impl RestoreRule {
+
+    fn from_name(name:&str) -> Option<Self> {
+        match name {
+            "reset" => Some(RestoreRule::Reset),
+            "keep" => Some(RestoreRule::Keep),
+            "ahead" => Some(RestoreRule::Ahead),
+            _ => None
+        }
+    }
+
+}
+
+/// restore rule of each kind of entity
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+#[serde(default)]
+pub struct RestoreRules {
+    pub enemies:    RestoreRule,
+    pub blocks:     RestoreRule
+}
+
+impl Default for RestoreRules {
+
+    fn default() -> Self {
+        Self {
+            enemies: RestoreRule::Ahead,
+            blocks: RestoreRule::Keep
+        }
+    }
+
+}
+
+
+/// where a tile layer is drawn compared to the entities
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Default,Deserialize,Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Depth {
+    /// far behind the play field, like the hills and the clouds
+    Background,
+    /// the play field, drawn behind the entities
+    #[default]
+    Midground,
+    /// drawn in front of the entities
+    Foreground,
+    /// drawn on top of everything else
+    Overlay
+}
+
+impl Depth {
+
+    pub fn from_name(name:&str) -> Option<Self> {
+        match name {
+            "background" => Some(Depth::Background),
+            "midground" => Some(Depth::Midground),
+            "foreground" => Some(Depth::Foreground),
+            "overlay" => Some(Depth::Overlay),
+            _ => None
+        }
+    }
+
+}
+
+fn default_parallax() -> f32 { 1.0 }
+
+/// extra tile layer of a csv level
+#[derive(Debug,Clone,PartialEq,Deserialize,Serialize)]
+pub struct LayerData {
+    pub name:       String,
+    /// csv file of the layer in the assets directory
+    pub file:       String,
+    #[serde(default)]
+    pub depth:      Depth,
+    /// how fast the layer scroll compared to the play field, 0 stay on screen
+    #[serde(default = "default_parallax")]
+    pub parallax:   f32
+}
+
+
+/// every entity of a level, read from the json file next to the level csv
+#[derive(Debug,Clone,PartialEq,Deserialize,Serialize)]
+pub struct LevelData {
+    pub player:         Point,
+    /// the player win when he reach this x position
+    pub goal:           f32,
+    #[serde(default)]
+    pub goombas:        Vec<GoombaData>,
+    #[serde(default)]
+    pub blocks:         Vec<BlockData>,
+    #[serde(default,skip_serializing_if = "Vec::is_empty")]
+    pub platforms:      Vec<PlatformData>,
+    #[serde(default,skip_serializing_if = "Vec::is_empty")]
+    pub coins:          Vec<Point>,
+    #[serde(default)]
+    pub checkpoints:    Vec<Checkpoint>,
+    #[serde(default)]
+    pub restore:        RestoreRules,
+    /// color of the sky as #rrggbb
+    #[serde(default,skip_serializing_if = "Option::is_none")]
+    pub background:     Option<String>,
+    /// tile layers drawn with the one of the csv file
+    #[serde(default,skip_serializing_if = "Vec::is_empty")]
+    pub layers:         Vec<LayerData>
+}
+
+impl LevelData {
+
+    pub fn load(path:&str) -> GResult<Self> {
+
+        let content = fs::read_to_string(path)?;
+
+        let mut data: LevelData = match serde_json::from_str(&content) {
+            Ok(data) => data,
+            Err(e) => bail!("invalid level file '{}': {}",path,e)
+        };
+
+        // the player always reach the checkpoints from left to right
+        data.checkpoints.sort_by(|a,b| a.x.total_cmp(&b.x));
+
+        Ok(data)
+
+    }
+
+    /// read the entities from the object layers of a tiled map, the class of an object tell what it is
+    pub fn from_tiled(map:&TiledMap) -> GResult<Self> {
+
+        let mut player = None;
+        let mut goal = None;
+        let mut goombas = Vec::new();
+        let mut blocks = Vec::new();
+        let mut platforms = Vec::new();
+        let mut coins = Vec::new();
+        let mut checkpoints = Vec::new();
+
+        // tiled count y from the top of the map, the first row of the grid start one tile above 0
+        let top = (map.height as f32 + 1.0) * TILE_SIZE;
+
+        for object in map.objects.iter() {
+
+            let x = object.x;
+            let y = top - object.get_bottom();
+
+            match object.kind.as_str() {
+
+                "player" => player = Some(Point { x, y }),
+                "goal" => goal = Some(x),
+                "goomba" => {
+
+                    let direction = match object.properties.get_str("direction") {
+                        Some(name) => match Facing::from_name(name) {
+                            Some(direction) => direction,
+                            None => bail!("goomba '{}' has an invalid direction '{}'",object.name,name)
+                        },
+                        None => Facing::Left
+                    };
+
+                    goombas.push(GoombaData {
+                        x,
+                        y,
+                        direction,
+                        wake_x: object.properties.get_f32("wake_x")
+                    });
+
+                },
+                "block" => {
+
+                    let kind = match object.properties.get_str("kind") {
+                        Some(name) => match BlockKind::from_name(name) {
+                            Some(kind) => kind,
+                            None => bail!("block '{}' has an invalid kind '{}'",object.name,name)
+                        },
+                        None => BlockKind::Mystery
+                    };
+
+                    blocks.push(BlockData { x, y, kind });
+
+                },
+                "platform" => platforms.push(PlatformData {
+                    x,
+                    y,
+                    width: ((object.width / TILE_SIZE).round() as u32).max(1),
+                    motion: PlatformMotion::from_properties(&object.name,&object.properties)?
+                }),
+                "coin" => coins.push(Point { x, y }),
+                "checkpoint" => checkpoints.push(Checkpoint { x, y }),
+                kind => eprintln!("ignore object '{}' with the unknown class '{}'",object.name,kind)
+
+            }
+
+        }
+
+        let mut restore = RestoreRules::default();
+
+        for (name,rule) in [("restore_enemies",&mut restore.enemies),("restore_blocks",&mut restore.blocks)] {
+            if let Some(value) = map.properties.get_str(name) {
+                match RestoreRule::from_name(value) {
+                    Some(value) => *rule = value,
+                    None => bail!("invalid value '{}' for the map property '{}'",value,name)
+                }
+            }
+        }
+
+        let player = match player {
+            Some(player) => player,
+            None => bail!("the map has no object of class 'player'")
+        };
+
+        let goal = match goal {
+            Some(goal) => goal,
+            None => bail!("the map has no object of class 'goal'")
+        };
+
+        checkpoints.sort_by(|a,b| a.x.total_cmp(&b.x));
+
+        Ok(Self {
+            player,
+            goal,
+            goombas,
+            blocks,
+            platforms,
+            coins,
+            checkpoints,
+            restore,
+            // the tiled map keep its own layers and background
+            background: None,
+            layers: Vec::new()
+        })
+
+    }
+
+    pub fn save(&self,path:&str) -> GResult<()> {
+
+        let content = match serde_json::to_string_pretty(self) {
+            Ok(content) => content,
+            Err(e) => bail!("can't write level file '{}': {}",path,e)
+        };
+
+        fs::write(path,content + "\n")?;
+
+        Ok(())
+
+    }
+
+}
@@ -1,11 +1,34 @@
 #[macro_use]
 extern crate simple_error;
 
+mod animation;
+mod assets;
+mod atlas;
+mod audio;
+mod bench;
+mod cli;
+mod collision;
+mod component;
+mod console;
+mod debug;
 mod declaration;
+mod editor;
 mod entity;
+mod event;
+mod fireball;
+mod level;
 mod map;
+mod physics;
+mod platform;
 mod state;
 mod player;
+mod replay;
+mod save;
+mod snapshot;
+mod system;
+mod tiled;
+mod timer;
+mod world;
 
 
 
@@ -14,11 +37,15 @@ use macroquad::prelude::*;
 
 
 fn window_conf() -> Conf {
+
+    // a bad option is reported once the game start
+    let options = cli::Options::from_env().unwrap_or_default();
+
     Conf {
         window_title: "Rusty Mario".to_owned(),
-        fullscreen: false,
-        window_width: declaration::WIDTH as i32,
-        window_height: declaration::HEIGHT as i32,
+        fullscreen: options.fullscreen,
+        window_width: (declaration::WIDTH * options.scale) as i32,
+        window_height: (declaration::HEIGHT * options.scale) as i32,
         window_resizable: false,
         ..Default::default()
     }
@@ -28,20 +55,56 @@ fn window_conf() -> Conf {
 #[macroquad::main(window_conf)]
 async fn main() -> lib_game::GResult<()> {
 
-    let bkg_col = Color::new(0.41,0.54,1.0,1.0);
+    let options = match cli::Options::from_env() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}",e);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = assets::init(options.assets.as_deref()) {
+        eprintln!("{}",e);
+        std::process::exit(1);
+    }
+
+    let level = options.level.as_deref().unwrap_or(cli::DEFAULT_LEVEL);
+
+    if let Some(frames) = options.bench {
+        return bench::run(level,frames).await;
+    }
+
+    let mut state = state::State::init(&options).await?;
+
+    // run the simulation as fast as possible, nothing is drawn
+    if let Some(frames) = options.headless {
+
+        for _ in 0..frames {
+            state.handle_input();
+
+            state.update();
+            state.sync_world().await?;
+        }
+
+        println!("{}",state.describe());
+
+        return state.finish();
+    }
 
-    let mut state = state::State::init().await?;
+    // the recording is written before the window close
+    prevent_quit();
 
-    loop {
-        clear_background(bkg_col);
+    while !is_quit_requested() {
         state.handle_input();
 
         state.update();
+        state.sync_world().await?;
 
         state.render();
 
         next_frame().await;
     }
 
+    state.finish()
 
 }
@@ -1,115 +1,706 @@
 
-use crate::declaration::{ASSETS_DIR, TILE_DIR, TILE_SIZE};
-use crate::entity::{Entity, Tile};
+use crate::atlas::AtlasBuilder;
+use crate::assets::{get_assets_dir, get_tile_dir};
+use crate::declaration::{TILE_SIZE, NORM_WIDTH_TILE_SIZE, NORM_HEIGHT_TILE_SIZE};
+use crate::entity::{Entity, Tile, get_normalized_position, get_view};
+use crate::collision::{Solid, SolidKind};
+use crate::level::{Depth, LevelData};
+use crate::tiled::TiledMap;
 
 use lib_game::GResult;
-use lib_game::loader::{Level,load_level,load_tileset,Tileset};
+use lib_game::loader::{Level,load_level,load_tileset};
+use lib_game::vector::Vec2;
+
+use macroquad::prelude::{
+    Camera2D,
+    Color,
+    Image,
+    Texture2D,
+    WHITE,
+    load_image,
+    set_camera,
+    vec2,
+    vec3,
+    Rect as r
+};
+use macroquad::models::{Mesh, Vertex, draw_mesh};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
 
 
 pub type TileMap = Vec<Tile>;
 
-/// initialise all the tile with the buffer tile id
-async fn load_tilemap(tileset:&Tileset,lvl_map:&Level) -> GResult<TileMap> {
+
+/// what every tile with the same id share
+#[derive(Clone,Copy)]
+pub struct TileKind {
+    pub texture:    Texture2D,
+    /// part of the texture to draw, the whole texture when none
+    pub source:     Option<r>,
+    pub wall:       bool
+}
+
+/// every tile of the tileset by id, the texture are loaded only once
+pub type Palette = BTreeMap<i32,TileKind>;
+
+
+/// id of the cell without any tile
+pub const EMPTY_TILE: i32 = -1;
+
+/// sky of a level that doesn't choose its own
+const DEFAULT_BACKGROUND: Color = Color::new(0.41,0.54,1.0,1.0);
+
+/// number of column drawn by one mesh
+const CHUNK_COLUMNS: usize = 16;
+
+
+/// load the image of every tile of the tileset
+async fn load_tile_images() -> GResult<AtlasBuilder> {
+
+    let tileset = load_tileset(&get_tile_dir())?;
+
+    let mut images = AtlasBuilder::new();
+
+    for info in tileset.iter() {
+
+        let image = load_image(info.get_src()).await?;
+
+        images.add(info.get_id(),&image,None,info.is_a_wall());
+
+    }
+
+    Ok(images)
+
+}
+
+/// load the image of every tile of the tilesets of a tiled map, the tiles are known by their gid
+async fn load_tiled_images(map:&TiledMap) -> GResult<AtlasBuilder> {
+
+    // the tiles of a tileset image share the same file
+    let mut files: HashMap<&str,Image> = HashMap::new();
+    let mut images = AtlasBuilder::new();
+
+    for tile in map.tiles.iter() {
+
+        if !files.contains_key(tile.image.as_str()) {
+            files.insert(&tile.image,load_image(&tile.image).await?);
+        }
+
+        images.add(
+            tile.gid as i32,
+            &files[tile.image.as_str()],
+            tile.source.map(|(x,y,w,h)| r::new(x,y,w,h)),
+            tile.properties.get_bool("wall").unwrap_or(false)
+        );
+
+    }
+
+    Ok(images)
+
+}
+
+/// position of the bottom left corner of a cell, the first row is the top of the level
+fn get_cell_position(lvl_map:&Level,col:usize,row:usize) -> Vec2 {
+    Vec2::new(
+        TILE_SIZE * col as f32,
+        TILE_SIZE * (lvl_map.len() - row) as f32
+    )
+}
+
+/// initialise all the tile with the buffer tile id, column by column
+/// return the tiles and the index of the first tile of each column
+fn load_tilemap(palette:&Palette,lvl_map:&Level) -> GResult<(TileMap,Vec<usize>)> {
 
     let mut tilemap:TileMap = Vec::new();
 
-    //let mut x:f32 = 14.0 * TILE_SIZE;
-    let mut y:f32 = 14.0 * TILE_SIZE;
+    let cols = lvl_map.first().map_or(0,|row| row.len());
+    let mut columns = Vec::with_capacity(cols + 1);
+
+    for col_ctn in 0..cols {
+
+        columns.push(tilemap.len());
 
-    for row in lvl_map.iter() {
-        for (col_ctn,col) in row.iter().enumerate() {
+        for (row_ctn,row) in lvl_map.iter().enumerate() {
 
-            if col != &-1 {
-                let mut found = false;
+            let col = match row.get(col_ctn) {
+                Some(col) => col,
+                None => continue
+            };
 
-                for ti in tileset.iter() {
+            if *col != EMPTY_TILE {
 
-                    if &ti.get_id() == col {
+                let kind = match palette.get(col) {
+                    Some(kind) => kind,
+                    None => bail!("no tile with id '{}' exist",col)
+                };
 
+                let pos = get_cell_position(lvl_map,col_ctn,row_ctn);
 
-                        let px = TILE_SIZE * col_ctn as f32;
+                tilemap.push(Tile::new(kind,pos.x,pos.y));
 
-                        let tile = Tile::new(ti, px, y).await?;
-                        tilemap.push(tile);
+            }
+        }
+    }
 
-                        found = true;
-                        break;
+    columns.push(tilemap.len());
 
-                    }
+    Ok((tilemap,columns))
 
-                }
+}
+
+/// number of column of a mesh, a mesh can't have more vertex than an u16 can count
+fn get_chunk_columns(lvl_map:&Level) -> usize {
+    (u16::MAX as usize / 4 / lvl_map.len().max(1)).clamp(1,CHUNK_COLUMNS)
+}
+
+/// two triangles of one tile, the texture is flipped like the one of the entities
+fn push_quad(mesh:&mut Mesh,kind:&TileKind,pos:&Vec2) {
+
+    let (x,y) = get_normalized_position(pos);
+    let (w,h) = (NORM_WIDTH_TILE_SIZE,NORM_HEIGHT_TILE_SIZE);
+
+    let (tw,th) = (kind.texture.width(),kind.texture.height());
+    let source = kind.source.unwrap_or(r::new(0.0,0.0,tw,th));
+
+    let (u0,v0) = (source.x / tw,source.y / th);
+    let (u1,v1) = ((source.x + source.w) / tw,(source.y + source.h) / th);
+
+    let first = mesh.vertices.len() as u16;
+
+    mesh.vertices.extend([
+        Vertex { position: vec3(x,y,0.0), uv: vec2(u0,v1), color: WHITE },
+        Vertex { position: vec3(x + w,y,0.0), uv: vec2(u1,v1), color: WHITE },
+        Vertex { position: vec3(x + w,y + h,0.0), uv: vec2(u1,v0), color: WHITE },
+        Vertex { position: vec3(x,y + h,0.0), uv: vec2(u0,v0), color: WHITE }
+    ]);
+
+    mesh.indices.extend([first,first + 1,first + 2,first,first + 2,first + 3]);
+
+}
+
+/// one mesh for each group of columns, every tile must come from the same texture
+fn load_meshes(palette:&Palette,lvl_map:&Level) -> GResult<Vec<Mesh>> {
+
+    let cols = lvl_map.first().map_or(0,|row| row.len());
+    let chunk_columns = get_chunk_columns(lvl_map);
+
+    let texture = palette.values().next().map(|kind| kind.texture);
+
+    let mut meshes = Vec::with_capacity(cols.div_ceil(chunk_columns));
+
+    for first_col in (0..cols).step_by(chunk_columns) {
 
-                if !found {
-                    bail!("no tile with id '{}' exist",col);
+        let mut mesh = Mesh {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            texture
+        };
+
+        for col_ctn in first_col..(first_col + chunk_columns).min(cols) {
+            for (row_ctn,row) in lvl_map.iter().enumerate() {
+
+                let id = match row.get(col_ctn) {
+                    Some(id) if *id != EMPTY_TILE => id,
+                    _ => continue
+                };
+
+                let kind = match palette.get(id) {
+                    Some(kind) => kind,
+                    None => bail!("no tile with id '{}' exist",id)
+                };
+
+                if Some(kind.texture) != texture {
+                    bail!("the tile '{}' is not in the atlas",id);
                 }
 
+                push_quad(&mut mesh,kind,&get_cell_position(lvl_map,col_ctn,row_ctn));
 
             }
         }
-        y -= TILE_SIZE;
+
+        meshes.push(mesh);
+
     }
 
-    Ok(tilemap)
+    Ok(meshes)
 
 }
 
 
 
-pub struct Map {
+/// true when the assets directory has a level with this name
+pub fn level_exists(name:&str) -> bool {
+    ["csv","tmx","tmj"].iter().any(|ext| Path::new(&format!("{}/{}.{}",get_assets_dir(),name,ext)).exists())
+}
+
+/// level after this one, lvl-1-2 come after lvl-1-1, none when it doesn't exist
+pub fn get_next_level(name:&str) -> Option<String> {
+
+    let (prefix,number) = name.rsplit_once('-')?;
+    let next = format!("{}-{}",prefix,number.parse::<u32>().ok()? + 1);
+
+    if level_exists(&next) { Some(next) } else { None }
+
+}
+
 
-    level:          Level,
+/// file the tiles of the level come from
+enum MapSource {
+    /// one layer of tile id from the tileset of the tile directory, the entities are in a json file
+    Csv,
+    /// map made with Tiled, the entities are in its object layers
+    Tiled(String)
+}
+
+
+/// parse a color written as #rrggbb or #aarrggbb
+fn parse_color(hex:&str) -> GResult<Color> {
+
+    let digits = hex.trim_start_matches('#');
+
+    let value = match u32::from_str_radix(digits,16) {
+        Ok(value) if digits.len() == 6 || digits.len() == 8 => value,
+        _ => bail!("invalid color '{}'",hex)
+    };
+
+    let alpha = if digits.len() == 8 { (value >> 24) & 0xFF } else { 0xFF };
+
+    Ok(Color::from_rgba(
+        ((value >> 16) & 0xFF) as u8,
+        ((value >> 8) & 0xFF) as u8,
+        (value & 0xFF) as u8,
+        alpha as u8
+    ))
+
+}
+
+
+/// one grid of tile, drawn on top of the previous layers of the same depth
+pub struct MapLayer {
+    name:           String,
+    grid:           Level,
     tilemap:        TileMap,
+    /// index in the tilemap of the first tile of each column, and the number of tile at the end
+    columns:        Vec<usize>,
+    /// the tiles of each group of column in one mesh
+    meshes:         Vec<Mesh>,
+    depth:          Depth,
+    /// how fast the layer scroll compared to the play field
+    parallax:       f32
+}
+
+impl MapLayer {
+
+    fn new(name:&str,grid:Level,depth:Depth,parallax:f32) -> Self {
+        Self {
+            name: name.to_string(),
+            grid,
+            tilemap: TileMap::new(),
+            columns: vec![0],
+            meshes: Vec::new(),
+            depth,
+            parallax
+        }
+    }
+
+    /// tiles of the columns between the two x
+    fn get_tiles(&self,left:f32,right:f32) -> &[Tile] {
+
+        let last = self.columns.len() - 1;
+
+        let first_col = ((left / TILE_SIZE).floor().max(0.0) as usize).min(last);
+        let last_col = ((right / TILE_SIZE).ceil().max(0.0) as usize).clamp(first_col,last);
+
+        &self.tilemap[self.columns[first_col]..self.columns[last_col]]
+
+    }
+
+    /// meshes of the columns between the two x
+    fn get_meshes(&self,left:f32,right:f32) -> &[Mesh] {
+
+        let chunk_columns = get_chunk_columns(&self.grid) as f32 * TILE_SIZE;
+        let last = self.meshes.len();
+
+        let first_mesh = ((left / chunk_columns).floor().max(0.0) as usize).min(last);
+        let last_mesh = ((right / chunk_columns).ceil().max(0.0) as usize).clamp(first_mesh,last);
+
+        &self.meshes[first_mesh..last_mesh]
+
+    }
+
+    /// only the layers that move with the play field can stop an entity
+    fn is_solid(&self) -> bool {
+        self.parallax == 1.0 && matches!(self.depth,Depth::Midground | Depth::Foreground)
+    }
+
+    /// create the tiles, and the meshes when batched, from the grid
+    fn build(&mut self,palette:&Palette,batched:bool) -> GResult<()> {
+
+        (self.tilemap,self.columns) = load_tilemap(palette,&self.grid)?;
+
+        self.meshes = if batched { load_meshes(palette,&self.grid)? } else { Vec::new() };
+
+        Ok(())
+
+    }
+
+}
+
+
+pub struct Map {
+
+    name:           String,
+    /// directory of the files of the level
+    dir:            String,
+    source:         MapSource,
+    layers:         Vec<MapLayer>,
+    /// layer changed by the editor
+    main:           usize,
+    palette:        Palette,
+    /// image of every tile, the palette is made from it
+    images:         AtlasBuilder,
+    /// draw the meshes of the atlas instead of each tile on its own
+    batched:        bool,
+    solids:         Vec<Solid>,
+    background:     Color,
+    /// entities of the level
+    objects:        Option<LevelData>
 
 }
 
 impl Map {
 
-    pub fn init() -> GResult<Self> {
+    /// find the file of the level, the level is a level file or the name of a level of the assets directory,
+    /// for a name a tiled map is used before a csv one
+    pub fn init(level:&str) -> GResult<Self> {
+
+        let path = Path::new(level);
+        let extension = path.extension().and_then(|ext| ext.to_str());
+
+        let (dir,name) = match extension {
+            Some("csv" | "tmx" | "tmj") => {
+
+                if !path.is_file() {
+                    bail!("the level file '{}' doesn't exist",level);
+                }
+
+                let dir = match path.parent().and_then(|dir| dir.to_str()) {
+                    Some("") | None => ".".to_string(),
+                    Some(dir) => dir.to_string()
+                };
+
+                // the other files of the level are named after its file
+                let name = path.file_stem().and_then(|name| name.to_str()).unwrap_or(level);
 
-        let plvl = format!("{}/lvl-1-1.csv",ASSETS_DIR);
-        let level = load_level(&plvl)?;
-        let tilemap = TileMap::new();
+                (dir,name.to_string())
+
+            },
+            _ => (get_assets_dir().to_string(),level.to_string())
+        };
+
+        let tiled = match extension {
+            Some("tmx" | "tmj") => Some(level.to_string()),
+            Some("csv") => None,
+            _ => ["tmx","tmj"].iter()
+                .map(|ext| format!("{}/{}.{}",dir,name,ext))
+                .find(|path| Path::new(path).exists())
+        };
+
+        let (source,layers) = match tiled {
+            Some(path) => (MapSource::Tiled(path),Vec::new()),
+            None => {
+                let plvl = format!("{}/{}.csv",dir,name);
+                let level = load_level(&plvl)?;
+
+                (MapSource::Csv,vec![MapLayer::new("main",level,Depth::Midground,1.0)])
+            }
+        };
 
 
         Ok(Self {
-            tilemap,
-            level,
+            name,
+            dir,
+            source,
+            layers,
+            main: 0,
+            palette: Palette::new(),
+            images: AtlasBuilder::new(),
+            batched: true,
+            solids: Vec::new(),
+            background: DEFAULT_BACKGROUND,
+            objects: None
         })
     }
 
     pub async fn load(&mut self) -> GResult<()> {
 
+        match &self.source {
+            MapSource::Csv => {
+                self.images = load_tile_images().await?;
+                self.load_csv_layers()?;
+            },
+            MapSource::Tiled(path) => {
+                let map = TiledMap::load(path)?;
+                self.load_tiled(&map).await?;
+            }
+        }
+
+        self.palette = self.images.build();
+
+        self.build()
+
+    }
+
+    /// choose between the atlas meshes and one draw by tile, the tiles are loaded again
+    pub fn set_batched(&mut self,batched:bool) -> GResult<()> {
+
+        self.palette = if batched { self.images.build() } else { self.images.build_separate() };
+        self.batched = batched;
+
+        self.build()
+
+    }
+
+    async fn load_tiled(&mut self,map:&TiledMap) -> GResult<()> {
+
+        if map.tile_width != TILE_SIZE || map.tile_height != TILE_SIZE {
+            bail!("the tiles of the map must be {} pixel wide",TILE_SIZE);
+        }
+
+        if map.tile_layers.is_empty() {
+            bail!("the map has no tile layer");
+        }
 
-        let tileset = load_tileset(TILE_DIR)?;
+        self.images = load_tiled_images(map).await?;
 
+        self.layers = map.tile_layers.iter()
+            .map(|layer| {
 
-        self.tilemap = load_tilemap(&tileset,&self.level).await?;
+                let grid = layer.gids.chunks(map.width)
+                    .map(|row| row.iter().map(|gid| if *gid == 0 { EMPTY_TILE } else { *gid as i32 }).collect())
+                    .collect();
+
+                let depth = match layer.properties.get_str("depth") {
+                    Some(name) => match Depth::from_name(name) {
+                        Some(depth) => depth,
+                        None => bail!("layer '{}' has an invalid depth '{}'",layer.name,name)
+                    },
+                    None => Depth::Midground
+                };
+
+                Ok(MapLayer::new(&layer.name,grid,depth,layer.parallax))
+
+            })
+            .collect::<GResult<Vec<_>>>()?;
+
+        // the layer the editor paint is the one marked as main in Tiled, or the first one
+        self.main = map.tile_layers.iter()
+            .position(|layer| layer.properties.get_bool("main").unwrap_or(false))
+            .unwrap_or(0);
+
+        if let Some(color) = &map.background {
+            self.background = parse_color(color)?;
+        }
+
+        self.objects = Some(LevelData::from_tiled(map)?);
 
         Ok(())
 
     }
 
-    pub fn render(&self) {
+    /// add the extra layers listed in the json file of a csv level
+    fn load_csv_layers(&mut self) -> GResult<()> {
 
-        for tile in self.tilemap.iter() {
+        let level = LevelData::load(&self.get_file("json"))?;
 
-            tile.draw();
+        for layer in level.layers.iter() {
 
+            let grid = load_level(&format!("{}/{}",self.dir,layer.file))?;
+
+            self.layers.push(MapLayer::new(&layer.name,grid,layer.depth,layer.parallax));
+
+        }
+
+        if let Some(color) = &level.background {
+            self.background = parse_color(color)?;
         }
 
+        self.objects = Some(level);
+
+        Ok(())
+
     }
 
-    pub fn get_tiles(&self) -> &TileMap { &self.tilemap }
+    /// entities of the level, from the tiled map or from the json file next to the csv
+    pub fn load_level_data(&self) -> GResult<LevelData> {
+        match &self.objects {
+            Some(level) => Ok(level.clone()),
+            None => bail!("the map '{}' is not loaded",self.name)
+        }
+    }
 
+    /// create the tiles and the solids from the level grids
+    fn build(&mut self) -> GResult<()> {
 
+        for layer in self.layers.iter_mut() {
+            layer.build(&self.palette,self.batched)?;
+        }
 
+        self.build_solids();
 
-}
+        Ok(())
+
+    }
+
+    /// create again the tiles of the main layer once the editor changed its grid
+    pub fn rebuild_main_layer(&mut self) -> GResult<()> {
+
+        self.layers[self.main].build(&self.palette,self.batched)?;
+
+        self.build_solids();
+
+        Ok(())
+
+    }
+
+    fn build_solids(&mut self) {
+
+        // only the wall can stop a moving entity
+        self.solids = self.layers.iter()
+            .filter(|layer| layer.is_solid())
+            .flat_map(|layer| layer.tilemap.iter())
+            .filter(|tile| tile.is_a_wall())
+            .map(|tile| Solid::new(*tile.get_rect(),SolidKind::Tile))
+            .collect();
+
+    }
+
+    /// draw every layer of the given depth, scroll is how far the camera moved from the start of the level
+    pub fn render(&self,depth:Depth,camera:&Camera2D,scroll:f32) {
+
+        for layer in self.layers.iter().filter(|layer| layer.depth == depth) {
+
+            // a slower layer is drawn with a camera that moved less
+            let moved = layer.parallax != 1.0;
+
+            let layer_camera = Camera2D {
+                offset: vec2(camera.offset.x + scroll * (1.0 - layer.parallax),camera.offset.y),
+                ..*camera
+            };
+
+            if moved {
+                set_camera(&layer_camera);
+            }
+
+            // only the columns inside the view are drawn
+            let (left,right) = get_view(&layer_camera);
+
+            if self.batched {
+                for mesh in layer.get_meshes(left,right).iter() {
+                    draw_mesh(mesh);
+                }
+            } else {
+                for tile in layer.get_tiles(left,right).iter() {
+                    tile.draw();
+                }
+            }
+
+            if moved {
+                set_camera(camera);
+            }
+
+        }
+
+    }
 
+    /// draw the layers of every depth
+    pub fn render_all(&self,camera:&Camera2D,scroll:f32) {
+        for depth in [Depth::Background,Depth::Midground,Depth::Foreground,Depth::Overlay] {
+            self.render(depth,camera,scroll);
+        }
+    }
 
+    pub fn get_background(&self) -> Color { self.background }
 
+    pub fn get_solids(&self) -> &[Solid] { &self.solids }
+    /// name of the level file, the other file of the level are named after it
+    pub fn get_name(&self) -> &str { &self.name }
+    /// path of the file of the level with the given extension
+    pub fn get_file(&self,extension:&str) -> String { format!("{}/{}.{}",self.dir,self.name,extension) }
+    pub fn get_palette(&self) -> &Palette { &self.palette }
+    /// name of the layer the editor paint
+    pub fn get_main_layer_name(&self) -> &str { &self.layers[self.main].name }
 
 
+    fn get_grid(&self) -> &Level { &self.layers[self.main].grid }
 
+    /// number of column and row of the level
+    pub fn get_size(&self) -> (usize,usize) {
+        let grid = self.get_grid();
+        (grid.first().map_or(0,|row| row.len()),grid.len())
+    }
 
+    /// column and row of the cell that contain the position
+    pub fn get_cell(&self,pos:&Vec2) -> Option<(usize,usize)> {
+
+        let (cols,rows) = self.get_size();
+
+        if pos.x < 0.0 || pos.y < 0.0 {
+            return None;
+        }
 
+        // the bottom row of the level start one tile above 0
+        let col = (pos.x / TILE_SIZE) as usize;
+        let height = (pos.y / TILE_SIZE) as usize;
+
+        if col >= cols || height == 0 || height > rows {
+            return None;
+        }
+
+        Some((col,rows - height))
+
+    }
+
+    pub fn get_cell_position(&self,col:usize,row:usize) -> Vec2 {
+        get_cell_position(self.get_grid(),col,row)
+    }
+
+    pub fn get_tile(&self,col:usize,row:usize) -> i32 { self.get_grid()[row][col] }
+
+    /// change the tile of a cell of the main layer, return the id it had before
+    /// only the grid change, the tiles are created again by rebuild_main_layer
+    pub fn set_tile(&mut self,col:usize,row:usize,id:i32) -> GResult<i32> {
+
+        if id != EMPTY_TILE && !self.palette.contains_key(&id) {
+            bail!("no tile with id '{}' exist",id);
+        }
+
+        let grid = &mut self.layers[self.main].grid;
+        let previous = grid[row][col];
+
+        grid[row][col] = id;
+
+        Ok(previous)
+
+    }
+
+    /// write the level grid back to its csv file
+    pub fn save(&self) -> GResult<()> {
+
+        if let MapSource::Tiled(path) = &self.source {
+            bail!("'{}' can only be changed in Tiled",path);
+        }
+
+        let content: String = self.get_grid().iter()
+            .map(|row| row.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",") + "\n")
+            .collect();
+
+        fs::write(self.get_file("csv"),content)?;
+
+        Ok(())
+
+    }
+
+}
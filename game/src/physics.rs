@@ -0,0 +1,146 @@
+
+use crate::assets::get_assets_dir;
+
+use lib_game::GResult;
+
+use serde::Deserialize;
+
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+
+/// number of frame between two check of the profile file modification time
+const POLL_INTERVAL: u8 = 30;
+
+
+/// every value that change how the game feel, read from the physics profile file
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize)]
+#[serde(default)]
+pub struct PhysicsProfile {
+    /// downward acceleration applied to the player
+    pub gravity:            f32,
+    /// downward acceleration applied to the enemies
+    pub enemy_gravity:      f32,
+    /// maximum falling speed of any entity
+    pub terminal_velocity:  f32,
+    /// upward velocity added at each frame of a jump
+    pub jump_factor:        f32,
+    /// number of frame a jump keep pushing the player up
+    pub jump_duration:      i32,
+    /// horizontal speed of the player
+    pub player_velocity:    f32,
+    /// horizontal speed of a goomba
+    pub goomba_velocity:    f32,
+    /// upward velocity the player get when he die
+    pub death_velocity:     f32,
+    /// velocity removed at each frame of the death animation
+    pub death_gravity:      f32,
+}
+
+impl Default for PhysicsProfile {
+
+    fn default() -> Self {
+        Self {
+            gravity: 10.0,
+            enemy_gravity: 5.0,
+            terminal_velocity: 5.0,
+            jump_factor: 0.5,
+            jump_duration: 10,
+            player_velocity: 2.0,
+            goomba_velocity: 1.0,
+            death_velocity: 10.0,
+            death_gravity: 0.5,
+        }
+    }
+
+}
+
+impl PhysicsProfile {
+
+    /// parse a profile from a json file, missing field keep their default value
+    pub fn load(path:&str) -> GResult<Self> {
+
+        let content = fs::read_to_string(path)?;
+
+        match serde_json::from_str(&content) {
+            Ok(profile) => Ok(profile),
+            Err(e) => bail!("invalid physics profile '{}': {}",path,e)
+        }
+
+    }
+
+}
+
+
+fn get_modified(path:&str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+
+/// keep the physics profile in sync with its file so it can be tweaked while the game run
+pub struct PhysicsWatcher {
+
+    path:       String,
+    profile:    PhysicsProfile,
+    modified:   Option<SystemTime>,
+    poll_ctn:   u8
+
+}
+
+impl PhysicsWatcher {
+
+    pub fn init() -> GResult<Self> {
+
+        let path = format!("{}/physics.json",get_assets_dir());
+
+        // the default profile is used when no file have been created
+        let profile = if Path::new(&path).exists() {
+            PhysicsProfile::load(&path)?
+        } else {
+            PhysicsProfile::default()
+        };
+
+        let modified = get_modified(&path);
+
+        Ok(Self {
+            path,
+            profile,
+            modified,
+            poll_ctn: 0
+        })
+
+    }
+
+    pub fn get_profile(&self) -> &PhysicsProfile { &self.profile }
+
+    /// reload the profile when its file have been modified since the last check
+    pub fn poll(&mut self) {
+
+        if self.poll_ctn < POLL_INTERVAL {
+            self.poll_ctn += 1;
+            return;
+        }
+
+        self.poll_ctn = 0;
+
+        let modified = get_modified(&self.path);
+
+        if modified.is_some() && modified != self.modified {
+
+            self.modified = modified;
+
+            // a bad edit should not crash the game, the last valid profile is kept
+            match PhysicsProfile::load(&self.path) {
+                Ok(profile) => {
+                    self.profile = profile;
+                    println!("physics profile reloaded from '{}'",self.path);
+                },
+                Err(e) => eprintln!("{}",e)
+            }
+
+        }
+
+    }
+
+}
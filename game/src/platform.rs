@@ -0,0 +1,202 @@
+
+use crate::collision::SolidKind;
+use crate::component::{Collider, Sprite};
+use crate::declaration::TILE_SIZE;
+use crate::level::PlatformMotion;
+use crate::physics::PhysicsProfile;
+use crate::world::{Behaviour, EntityId, World};
+
+use lib_game::GResult;
+use lib_game::vector::Vec2;
+
+use serde::{Deserialize, Serialize};
+
+
+/// height of the hitbox and the sprite of every platform
+const PLATFORM_HEIGHT: f32 = TILE_SIZE / 2.0;
+/// number of frame a falling platform wait once the player stand on it
+const FALL_DELAY: u32 = 15;
+/// a falling platform stop under this y, nothing can stand on it anymore
+const FALL_LIMIT: f32 = -4.0 * TILE_SIZE;
+
+
+/// fields of a platform that change while it move
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct PlatformState {
+    pub travelled:  f32,
+    pub fall_ctn:   Option<u32>
+}
+
+
+/// solid that move along its path, or fall once the player stand on it, and carry what stand on it
+pub struct Platform {
+    spos:       Vec2,
+    motion:     PlatformMotion,
+    /// distance done along the path since the start
+    travelled:  f32,
+    /// frames since the player stepped on a falling platform
+    fall_ctn:   Option<u32>
+}
+
+impl Platform {
+
+    /// add a platform of some tiles wide to the world
+    pub async fn spawn(world:&mut World,x:f32,y:f32,width:u32,motion:PlatformMotion) -> GResult<EntityId> {
+
+        let platform = Self {
+            spos: Vec2::new(x,y),
+            motion,
+            travelled: 0.0,
+            fall_ctn: None
+        };
+
+        let width = width.max(1) as f32 * TILE_SIZE;
+
+        let mut sprite = Sprite::load("platform").await?;
+        sprite.set_size(width,PLATFORM_HEIGHT);
+
+        let id = world.spawn();
+
+        world.positions[id] = Some(Vec2::new(x,y));
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.colliders[id] = Some(Collider::new(width,PLATFORM_HEIGHT,true));
+        world.sprites[id] = Some(sprite);
+        world.behaviours[id] = Some(Behaviour::Platform(platform));
+
+        Ok(id)
+
+    }
+
+    pub fn reset(&mut self,id:EntityId,world:&mut World) {
+
+        world.positions[id] = Some(self.spos);
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+
+        self.travelled = 0.0;
+        self.fall_ctn = None;
+
+    }
+
+    pub fn get_start(&self) -> Vec2 { self.spos }
+
+    pub fn get_state(&self) -> PlatformState {
+        PlatformState {
+            travelled: self.travelled,
+            fall_ctn: self.fall_ctn
+        }
+    }
+
+    pub fn set_state(&mut self,state:&PlatformState) {
+        self.travelled = state.travelled;
+        self.fall_ctn = state.fall_ctn;
+    }
+
+    /// position on the path compared to the start, after the given distance
+    fn get_offset(&self,travelled:f32) -> Vec2 {
+
+        // back and forth between 0 and the distance
+        let swing = |distance:f32| {
+
+            let length = distance.abs();
+
+            if length == 0.0 {
+                return 0.0;
+            }
+
+            let t = travelled.rem_euclid(length * 2.0);
+            let offset = if t <= length { t } else { length * 2.0 - t };
+
+            offset * distance.signum()
+
+        };
+
+        match self.motion {
+            PlatformMotion::Horizontal { distance, .. } => Vec2::new(swing(distance),0.0),
+            PlatformMotion::Vertical { distance, .. } => Vec2::new(0.0,swing(distance)),
+            PlatformMotion::Loop { radius, .. } if radius != 0.0 => {
+                // the start is the top of the circle
+                let angle = travelled / radius;
+                Vec2::new(radius * angle.sin(),radius * (angle.cos() - 1.0))
+            },
+            PlatformMotion::Elevator { distance, .. } => Vec2::new(0.0,travelled * distance.signum()),
+            PlatformMotion::Loop { .. } | PlatformMotion::Falling => Vec2::new(0.0,0.0)
+        }
+
+    }
+
+    /// the player landed on it during the last movement
+    fn is_stood_on(&self,id:EntityId,world:&World) -> bool {
+        world.get_player().is_some_and(|player| {
+            world.get_contacts(player).iter().any(|contact| contact.is_ground() && contact.kind == SolidKind::Entity(id))
+        })
+    }
+
+    /// choose the velocity that bring the platform to its next place on the path
+    pub fn update(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
+
+        if self.fall_ctn.is_none() && self.motion == PlatformMotion::Falling && self.is_stood_on(id,world) {
+            self.fall_ctn = Some(0);
+        }
+
+        let (position,velocity) = match (world.positions[id].as_mut(),world.velocities[id].as_mut()) {
+            (Some(position),Some(velocity)) => (position,velocity),
+            _ => return
+        };
+
+        match self.motion {
+
+            PlatformMotion::Falling => {
+
+                let fall_ctn = match self.fall_ctn.as_mut() {
+                    Some(fall_ctn) => fall_ctn,
+                    None => return
+                };
+
+                *fall_ctn += 1;
+
+                if *fall_ctn <= FALL_DELAY || position.y < FALL_LIMIT {
+                    velocity.y = 0.0;
+                    return;
+                }
+
+                let gravity_velocity = ((*fall_ctn - FALL_DELAY) as f32 / 60.0) * physics.enemy_gravity;
+                velocity.y = -gravity_velocity.min(physics.terminal_velocity);
+
+            },
+            PlatformMotion::Elevator { distance, speed } => {
+
+                self.travelled += speed.abs();
+
+                // back to the start without carrying anything
+                if self.travelled >= distance.abs() {
+                    self.travelled = 0.0;
+                    *position = self.spos;
+                    *velocity = Vec2::new(0.0,0.0);
+                    return;
+                }
+
+                self.move_to_offset(id,world);
+
+            },
+            PlatformMotion::Horizontal { speed, .. } | PlatformMotion::Vertical { speed, .. } | PlatformMotion::Loop { speed, .. } => {
+                self.travelled += speed;
+                self.move_to_offset(id,world);
+            }
+
+        }
+
+    }
+
+    /// the velocity is the gap to the next point of the path, so the rounding never add up
+    fn move_to_offset(&self,id:EntityId,world:&mut World) {
+
+        let offset = self.get_offset(self.travelled);
+
+        if let (Some(position),Some(velocity)) = (world.positions[id],world.velocities[id].as_mut()) {
+            velocity.x = self.spos.x + offset.x - position.x;
+            velocity.y = self.spos.y + offset.y - position.y;
+        }
+
+    }
+
+}
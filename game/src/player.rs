@@ -1,42 +1,20 @@
 
-use crate::entity::{
-    Entity,
-    Dynamic,
-    Goomba,
-    MysteryBlocks,
-    SpriteUser,
-    get_normalized_position
-};
-
-use crate::declaration::{
-    TILE_SIZE,
-    NORM_HEIGHT_TILE_SIZE,
-    NORM_WIDTH_TILE_SIZE,
-    GRAVITY,
-    ASSETS_DIR
-
-};
-use crate::map::TileMap;
-
-use lib_game::shape::{Rect, Shape};
+use crate::audio::SoundEffect;
+use crate::component::{Collider, Sprite};
+use crate::declaration::TILE_SIZE;
+use crate::physics::PhysicsProfile;
+use crate::world::{Behaviour, EntityId, World};
+
 use lib_game::vector::Vec2;
-use lib_game::sprite::SpriteSheet;
 use lib_game::GResult;
-use lib_game::collision;
 
-use macroquad::prelude::{
-    DrawTextureParams,
-    Texture2D,
-    load_texture,
-    draw_texture_ex,
-    vec2 as v2,
-    Rect as r,
-    WHITE
-};
+use macroquad::prelude::{Color, WHITE};
+
+use serde::{Deserialize, Serialize};
 
 
 
-#[derive(Debug,PartialEq)]
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
 pub enum PStatus {
     Walk,
     Idle,
@@ -44,492 +22,445 @@ pub enum PStatus {
     Dead
 }
 
-pub struct Player {
-    spos:               Vec2,
-    shape:              Rect,
-    velocity:           Vec2,
-    can_jump:           bool,
-    jumping:            bool,
-    jump_ctn:           i32,
-    fall_ctn:           i32,
-    spritesheet_src:    Texture2D,
-    draw_info:          DrawTextureParams,
-    spritesheet:        SpriteSheet,
-    walk_frame_ctn:     i8,
-    status:             PStatus,
-    dead_velocity:      f32
+/// number of frame the player can't be hurt after he shrink
+const INVULNERABLE_DURATION: u32 = 120;
+/// number of frame the star protect the player
+const STAR_DURATION: u32 = 10 * 60;
+/// the star flash slower during its last frames
+const STAR_ENDING: u32 = 2 * 60;
+/// color of the player while he can throw fireballs
+const FIRE_TINT: Color = Color::new(1.0,0.8,0.6,1.0);
+/// colors the player flash through while he has the star
+const STAR_PALETTE: [Color; 4] = [
+    Color::new(1.0,0.5,0.5,1.0),
+    Color::new(1.0,1.0,0.4,1.0),
+    Color::new(0.5,1.0,0.5,1.0),
+    Color::new(0.5,0.7,1.0,1.0)
+];
+
+
+/// what the player became by eating power-ups
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Power {
+    Small,
+    /// two tiles tall, an enemy only make him small again
+    Big,
+    /// as tall as big and he can throw fireballs
+    Fire
 }
 
-impl Player {
-
-    pub async fn new(start_pos:Vec2) -> GResult<Self> {
+impl Power {
 
-        let p = format!("{}/small-mario.png",ASSETS_DIR);
+    pub fn from_name(name:&str) -> Option<Self> {
+        match name {
+            "small" => Some(Power::Small),
+            "big" => Some(Power::Big),
+            "fire" => Some(Power::Fire),
+            _ => None
+        }
+    }
 
-        let spritesheet_src = load_texture(&p).await?;
-        let mut spritesheet = SpriteSheet::new(TILE_SIZE,TILE_SIZE);
+    /// height of the hitbox
+    fn get_height(&self) -> f32 {
+        match self {
+            Power::Small => TILE_SIZE,
+            Power::Big | Power::Fire => TILE_SIZE * 2.0
+        }
+    }
 
-        // sprite
-        spritesheet.add_sprite("idle",1,77.0,0.0,0.0)?; // default
-        spritesheet.add_sprite("walk",3,0.0,0.0,0.0)?;
-        spritesheet.add_sprite("jump",1,48.0,0.0,0.0)?;
-        spritesheet.add_sprite("dead",1,105.0,0.0,0.0)?;
+    /// color of the sprite, there is no fire sprite yet so the other one is tinted
+    fn get_tint(&self) -> Color {
+        match self {
+            Power::Small | Power::Big => WHITE,
+            Power::Fire => FIRE_TINT
+        }
+    }
 
-        // default frame
-        let frame = spritesheet.get_current_frame()?;
+}
 
 
+/// every field of the player that change while he play
+#[derive(Debug,Clone,PartialEq,Deserialize,Serialize)]
+pub struct PlayerState {
+    pub can_jump:           bool,
+    pub jumping:            bool,
+    pub jump_ctn:           i32,
+    pub fall_ctn:           i32,
+    pub status:             PStatus,
+    pub dead_velocity:      f32,
+    pub power:              Power,
+    pub invulnerable_ctn:   u32,
+    pub godmode:            bool,
+    pub noclip:             bool,
+    #[serde(default)]
+    pub star_ctn:           u32
+}
 
-        let dinfo = DrawTextureParams {
-            dest_size: Some(v2(NORM_WIDTH_TILE_SIZE,NORM_HEIGHT_TILE_SIZE)),
-            source: Some(r::new(frame.position.x,frame.position.y,frame.size.x,frame.size.y)),
-            rotation: 0.0,
-            flip_x: false,
-            flip_y: true,
-            pivot: None
-        };
 
+pub struct Player {
+    spos:               Vec2,
+    can_jump:           bool,
+    jumping:            bool,
+    jump_ctn:           i32,
+    fall_ctn:           i32,
+    status:             PStatus,
+    dead_velocity:      f32,
+    power:              Power,
+    /// frames left before an enemy can hurt the player again
+    invulnerable_ctn:   u32,
+    /// neither the enemies nor the timer can kill the player, a pit still can
+    godmode:            bool,
+    /// the player fly through every solid
+    noclip:             bool,
+    /// frames left before the star stop protecting the player
+    star_ctn:           u32
+}
 
+impl Player {
 
-        Ok(Self {
+    /// a small player standing at the start, without any component
+    pub fn new(start_pos:Vec2,physics:&PhysicsProfile) -> Self {
+        Self {
             spos:   start_pos,
-            shape: Rect::new(start_pos.x,start_pos.y,TILE_SIZE,TILE_SIZE),
-            velocity: Vec2::new(0.0,0.0),
             can_jump: true,
             jumping:  false,
             fall_ctn: 0,
-            spritesheet_src,
-            spritesheet,
-            draw_info:dinfo,
-            walk_frame_ctn: 0,
             status: PStatus::Walk,
-            jump_ctn: 10,
-            dead_velocity: 0.0
-        })
-
-    }
-
-    pub fn reset(&mut self) {
-        self.clear_velocity();
-        self.shape.pos = self.spos;
-        self.change_sprite_status(PStatus::Walk);
-    }
-
-    pub fn jump(&mut self) {
-
-        if self.can_jump {
-            self.jumping = true;
-            self.can_jump = false;
+            // the same as after a landing, the jump is as long as the profile say
+            jump_ctn: physics.jump_duration,
+            dead_velocity: 0.0,
+            power: Power::Small,
+            invulnerable_ctn: 0,
+            godmode: false,
+            noclip: false,
+            star_ctn: 0
         }
-
     }
-    pub fn flip_spritesheet(&mut self,y:bool) {
-        if self.spritesheet.should_flip() && !y || !self.spritesheet.should_flip() && y {
-            self.spritesheet.flip_sprite();
-        }
-
-    }
-
-    pub fn get_x(&self) -> f32 { self.shape.pos.x }
-    pub fn get_y(&self) -> f32 { self.shape.pos.y }
-    pub fn get_rect(&self) -> &Rect { &self.shape }
-    pub fn get_xvelocity(&self) -> f32 { self.velocity.x }
-    pub fn get_yvelocity(&self) -> f32 { self.velocity.y }
-    pub fn get_height(&self) -> f32 { self.shape.get_height() }
-
-    pub fn set_xvelocity(&mut self,x:f32) { self.velocity.x += x; }
-
-    pub fn is_dying(&self) -> bool { self.status == PStatus::Dead }
-
-    pub fn clear_velocity(&mut self) {
-        self.velocity.x = 0.0;
-        self.velocity.y = 0.0;
-    }
-
-
-    pub fn update_sprite(&mut self) -> GResult<()> {
-
-        match self.status {
-
-            PStatus::Idle => {
-
-                let frame = self.spritesheet.get_current_frame().unwrap();
-
-                self.draw_info.source = Some(r::new(
-                    frame.position.x,
-                    frame.position.y,
-                    frame.size.x,
-                    frame.size.y
-                ));
-
-            },
-            PStatus::Walk => {
-
-                if self.walk_frame_ctn > 3 {
-
-                    self.spritesheet.increment_current_sprite().unwrap();
-
-                    let frame = self.spritesheet.get_current_frame().unwrap();
-                    self.draw_info.flip_x = self.spritesheet.should_flip();
 
-                    self.draw_info.source = Some(r::new(
-                        frame.position.x,
-                        frame.position.y,
-                        frame.size.x,
-                        frame.size.y
-                    ));
+    /// add the player and all his component to the world
+    pub async fn spawn(world:&mut World,start_pos:Vec2,physics:&PhysicsProfile) -> GResult<EntityId> {
 
-                    self.walk_frame_ctn = 0;
+        let player = Self::new(start_pos,physics);
 
-                } else {
-                    self.walk_frame_ctn += 1;
-                }
+        let id = world.spawn();
 
+        world.positions[id] = Some(start_pos);
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.colliders[id] = Some(Collider::new(TILE_SIZE,TILE_SIZE,false));
+        world.sprites[id] = Some(Sprite::load("small-mario").await?);
+        world.behaviours[id] = Some(Behaviour::Player(player));
 
-            },
-            PStatus::Jump => {
+        world.set_player(id);
 
-                let frame = self.spritesheet.get_current_frame().unwrap();
-                self.draw_info.flip_x = self.spritesheet.should_flip();
-                self.draw_info.source = Some(r::new(
-                    frame.position.x,
-                    frame.position.y,
-                    frame.size.x,
-                    frame.size.y
-                ));
+        Ok(id)
 
-            },
-            PStatus::Dead => {
-
-                if self.dead_velocity > -5.0 {
-
-                    self.shape.pos.y += self.dead_velocity;
-                    self.dead_velocity -= 0.5;
-
-                } else {
-                    self.shape.pos.y -= 5.0;
-                }
+    }
 
+    pub fn reset(&mut self,id:EntityId,world:&mut World) {
 
-            }
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        world.positions[id] = Some(self.spos);
 
+        self.change_sprite_status(id,world,PStatus::Walk);
+        self.set_power(id,world,Power::Small);
+        self.invulnerable_ctn = 0;
+        self.star_ctn = 0;
 
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.visible = true;
         }
 
-
-
-        Ok(())
-
     }
 
-
-    fn die(&mut self) {
-
-        self.clear_velocity();
-        self.change_sprite_status(PStatus::Dead);
-        self.dead_velocity = 10.0;
-
+    /// same as reset but the player come back at the given position
+    pub fn respawn(&mut self,id:EntityId,world:&mut World,position:Vec2) {
+        self.reset(id,world);
+        world.positions[id] = Some(position);
     }
 
+    pub fn get_start(&self) -> Vec2 { self.spos }
 
-    fn check_collision_w_mblocks(&mut self, mblocks: &mut [MysteryBlocks]) {
-
-        let mut tmp_rect = self.shape;
-        tmp_rect.pos += self.velocity;
-
-
-        for block in mblocks.iter_mut() {
-
-            if collision::rect_vs_rect(&tmp_rect,block.get_rect()) {
+    pub fn jump(&mut self,world:&mut World) {
 
-                if collision::rect_vs_rect_horizontally(
-                    &self.shape,
-                    block.get_rect(),
-                    self.get_xvelocity()) {
-
-                    if self.velocity.x > 0.0 {
-
-                        self.shape.pos.x =block.get_rect().get_x() - self.shape.get_width();
-                        self.velocity.x = 0.0;
-
-                    } else {
+        if self.can_jump {
+            self.jumping = true;
+            self.can_jump = false;
 
-                        self.shape.pos.x = block.get_rect().get_max_x();
-                        self.velocity.x = 0.0;
+            world.play_sound(SoundEffect::Jump);
+        }
 
-                    }
+    }
 
-                }
+    pub fn is_dying(&self) -> bool { self.status == PStatus::Dead }
 
-                if collision::rect_vs_rect_vertically(
-                    &self.shape,
-                    block.get_rect(),
-                    self.get_yvelocity()) {
+    pub fn get_status(&self) -> &PStatus { &self.status }
+    pub fn can_jump(&self) -> bool { self.can_jump }
+    pub fn is_jumping(&self) -> bool { self.jumping }
+    /// number of frame since the player left the ground
+    pub fn get_fall_ctn(&self) -> i32 { self.fall_ctn }
+    pub fn get_power(&self) -> Power { self.power }
+    pub fn has_godmode(&self) -> bool { self.godmode }
+    pub fn has_noclip(&self) -> bool { self.noclip }
+    /// the enemies he touch are knocked off instead of hurting him
+    pub fn has_star(&self) -> bool { self.star_ctn > 0 }
+    pub fn give_star(&mut self) { self.star_ctn = STAR_DURATION; }
+
+    pub fn get_state(&self) -> PlayerState {
+        PlayerState {
+            can_jump: self.can_jump,
+            jumping: self.jumping,
+            jump_ctn: self.jump_ctn,
+            fall_ctn: self.fall_ctn,
+            status: self.status,
+            dead_velocity: self.dead_velocity,
+            power: self.power,
+            invulnerable_ctn: self.invulnerable_ctn,
+            godmode: self.godmode,
+            noclip: self.noclip,
+            star_ctn: self.star_ctn
+        }
+    }
 
-                    if self.get_yvelocity() < 0.0 {
+    /// the components are restored with the rest of the entity, only the player fields change
+    pub fn set_state(&mut self,state:&PlayerState) {
+        self.can_jump = state.can_jump;
+        self.jumping = state.jumping;
+        self.jump_ctn = state.jump_ctn;
+        self.fall_ctn = state.fall_ctn;
+        self.status = state.status;
+        self.dead_velocity = state.dead_velocity;
+        self.power = state.power;
+        self.invulnerable_ctn = state.invulnerable_ctn;
+        self.godmode = state.godmode;
+        self.noclip = state.noclip;
+        self.star_ctn = state.star_ctn;
+    }
 
-                        self.fall_ctn = 0;
-                        self.jumping = false;
-                        self.jump_ctn = 10;
-                        self.shape.pos.y = block.get_rect().get_y() + self.get_height();
-                        self.velocity.y = 0.0;
+    pub fn toggle_godmode(&mut self) { self.godmode = !self.godmode; }
 
-                    } else {
-                        block.collect();
-                        self.shape.pos.y = block.get_rect().get_y() - TILE_SIZE;
-                        self.velocity.y = 0.0;
-                        self.fall_ctn = 0;
-                        self.jumping = false;
+    pub fn toggle_noclip(&mut self,id:EntityId,world:&mut World) {
 
+        self.noclip = !self.noclip;
 
-                    }
+        if let Some(collider) = world.colliders[id].as_mut() {
+            collider.ghost = self.noclip;
+        }
 
-                }
+        // the fall start again from the place the player stop flying
+        self.fall_ctn = 0;
+        self.jumping = false;
 
+    }
 
-            }
+    /// change the size of the hitbox and the color, there is no big sprite yet so the small one is stretched
+    pub fn set_power(&mut self,id:EntityId,world:&mut World,power:Power) {
 
+        self.power = power;
 
+        if let Some(collider) = world.colliders[id].as_mut() {
+            collider.height = power.get_height();
         }
 
+        if let Some(sprite) = world.get_sprite_mut(id) {
+            sprite.set_size(TILE_SIZE,power.get_height());
+            sprite.tint = power.get_tint();
+        }
 
     }
 
-    fn check_collision_w_enemy(&mut self, goombas: &mut [Goomba]) {
-
-
-        for goomba in goombas.iter_mut() {
-
-            let mut tmp_rect = self.shape;
-            tmp_rect.pos += self.velocity;
+    /// an enemy touched the player, a big player only become small
+    pub fn hurt(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
 
-            if !goomba.is_dying() && collision::rect_vs_rect(&tmp_rect,goomba.get_rect()) {
-                if collision::rect_vs_rect_vertically(
-                    self.get_rect(),
-                    goomba.get_rect(),
-                    self.get_yvelocity()) {
-
-                    if self.velocity.y < 0.0 {
-                        goomba.die();
-                    } else {
-                        self.die();
-                        break;
-                    }
+        if self.godmode || self.invulnerable_ctn > 0 || self.has_star() {
+            return;
+        }
 
-                } else {
-                    self.die();
-                    break;
-                }
+        match self.power {
+            Power::Small => self.die(id,world,physics),
+            Power::Big | Power::Fire => {
+                self.set_power(id,world,Power::Small);
+                self.invulnerable_ctn = INVULNERABLE_DURATION;
 
+                world.play_sound(SoundEffect::Bump);
             }
-
         }
 
-
     }
 
-    fn check_collision_w_static(&mut self,tiles:&TileMap) {
-
-        let mut tmp_rect = *self.get_rect();
-        tmp_rect.pos += self.velocity;
 
-        for tile in tiles.iter() {
-
-            if tile.is_a_wall() && collision::rect_vs_rect(&tmp_rect, tile.get_rect()) {
-
-                if collision::rect_vs_rect_horizontally(
-                    self.get_rect(),
-                    tile.get_rect(),
-                    self.get_xvelocity()) {
-
-                    if self.get_xvelocity() > 0.0 {
-
-                        self.shape.pos.x = tile.get_rect().get_x() - self.get_rect().get_width();
-                        self.velocity.x = 0.0;
-
-                    } else  {
+    pub fn die(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
+        if !self.godmode {
+            self.start_death(id,world,physics.death_velocity);
+        }
+    }
 
-                        self.shape.pos.x = tile.get_rect().get_max_x();
-                        self.velocity.x = 0.0;
+    /// die without the jump of the death animation, the player keep falling in the pit
+    pub fn fall(&mut self,id:EntityId,world:&mut World) {
+        self.start_death(id,world,0.0);
+    }
 
-                    }
+    fn start_death(&mut self,id:EntityId,world:&mut World,velocity:f32) {
 
+        if self.status == PStatus::Dead {
+            return;
+        }
 
+        world.velocities[id] = Some(Vec2::new(0.0,0.0));
+        self.change_sprite_status(id,world,PStatus::Dead);
+        self.dead_velocity = velocity;
 
-                }
+        world.play_sound(SoundEffect::Death);
 
-                if collision::rect_vs_rect_vertically(
-                    self.get_rect(),
-                    tile.get_rect(),
-                    self.get_yvelocity()) {
+    }
 
-                    if self.get_yvelocity() < 0.0 {
 
-                        self.fall_ctn = 0;
-                        self.jumping = false;
-                        self.jump_ctn = 10;
-                        self.shape.pos.y = tile.get_rect().get_y() + self.get_height();
-                        self.velocity.y = 0.0;
+    /// add the jump and the gravity to the velocity given by the input
+    pub fn update(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
 
-                    } else {
-                        self.shape.pos.y = tile.get_rect().get_y() - 16.0;
-                        self.velocity.y = 0.0;
-                        self.fall_ctn = 0;
-                        self.jumping = false;
+        let (position,velocity) = match (world.positions[id].as_mut(),world.velocities[id].as_mut()) {
+            (Some(position),Some(velocity)) => (position,velocity),
+            _ => return
+        };
 
-                    }
+        if self.status == PStatus::Dead {
 
-                }
+            // the death animation move the player by itself, the input and the solid are ignored
+            velocity.x = 0.0;
+            velocity.y = 0.0;
 
+            if self.dead_velocity > -physics.terminal_velocity {
 
+                position.y += self.dead_velocity;
+                self.dead_velocity -= physics.death_gravity;
 
+            } else {
+                position.y -= physics.terminal_velocity;
             }
 
-
+            return;
 
         }
 
+        // the player blink while he can't be hurt
+        if self.invulnerable_ctn > 0 {
+            self.invulnerable_ctn -= 1;
 
+            let visible = self.invulnerable_ctn == 0 || (self.invulnerable_ctn / 4).is_multiple_of(2);
 
-    }
-
-
-    fn change_sprite_status(&mut self,status:PStatus) {
-
-        if self.status != status {
-            self.status = status;
-
-            match self.status {
-
-                PStatus::Walk => {
-                    self.spritesheet.change_current("walk").unwrap();
-                },
-                PStatus::Idle => {
-                    self.spritesheet.change_current("idle").unwrap();
-                },
-                PStatus::Jump => {
-                    self.spritesheet.change_current("jump").unwrap();
-                },
-                PStatus::Dead => {
-                    self.spritesheet.change_current("dead").unwrap();
-
-                }
-
+            if let Some(sprite) = world.sprites[id].as_mut() {
+                sprite.visible = visible;
             }
+        }
 
-            self.reload_sprite();
+        // the player flash through the palette while he has the star, the tint is set each frame so a rewind can't leave it
+        self.star_ctn = self.star_ctn.saturating_sub(1);
 
+        if let Some(sprite) = world.sprites[id].as_mut() {
+            sprite.tint = if self.star_ctn == 0 {
+                self.power.get_tint()
+            } else {
+                let speed = if self.star_ctn > STAR_ENDING { 4 } else { 8 };
+                STAR_PALETTE[(self.star_ctn / speed) as usize % STAR_PALETTE.len()]
+            };
         }
-    }
 
+        // the velocity only come from the input, the gravity and the jump don't apply
+        if self.noclip {
+            return;
+        }
 
-}
+        if self.jumping {
 
+            if self.jump_ctn >= -physics.jump_duration {
+                velocity.y += (self.jump_ctn + physics.jump_duration) as f32 * physics.jump_factor;
+                self.jump_ctn -= 1;
 
-impl Entity for Player {
+            } else {
+                self.jumping = false;
+                self.jump_ctn = 0;
+            }
 
-    fn draw(&self) {
+        }
 
-        let (nx,ny) = get_normalized_position(&self.shape.pos);
+        let mut gravity_velocity = (self.fall_ctn as f32 / 60.0 ) * -physics.gravity;
+        if gravity_velocity < -physics.terminal_velocity {
+            gravity_velocity = -physics.terminal_velocity;
+        }
 
-        draw_texture_ex(
-            self.spritesheet_src,
-            nx,
-            ny,
-            WHITE,
-            self.draw_info.clone()
-        );
+        self.fall_ctn += 1;
+        velocity.y += gravity_velocity;
 
+        if velocity.x + position.x < 0.0 {
+            velocity.x = 0.0;
+        }
 
     }
 
+    /// react to every solid the player touched on the way and choose the sprite to show
+    pub fn after_move(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
 
-}
-
-impl Dynamic<(&TileMap,&mut [MysteryBlocks],&mut [Goomba])> for Player {
-    fn reset(&mut self) {
-        self.clear_velocity();
-        self.shape.pos = self.spos;
-        self.change_sprite_status(PStatus::Walk);
-
-    }
-
-    fn update(&mut self, entity: (&TileMap, &mut [MysteryBlocks], &mut [Goomba])) {
+        if self.status == PStatus::Dead {
+            return;
+        }
 
-        let (tiles,mblocks,goombas) = entity;
+        for contact in world.get_contacts(id).iter() {
 
-        if self.status != PStatus::Dead {
+            if contact.is_ground() {
 
-            if self.jumping {
+                self.fall_ctn = 0;
+                self.jumping = false;
+                self.jump_ctn = physics.jump_duration;
 
-                if self.jump_ctn >= -10 {
-                    self.velocity.y += (self.jump_ctn as f32 + 10.0) * 0.5;
-                    self.jump_ctn -= 1;
+            } else if contact.is_ceiling() {
 
-                } else {
-                    self.jumping = false;
-                    self.jump_ctn = 0;
-                }
+                self.fall_ctn = 0;
+                self.jumping = false;
 
             }
 
-            let mut gravity_velocity = (self.fall_ctn as f32 / 60.0 ) * -GRAVITY;
-            if gravity_velocity < -5.0 {
-                gravity_velocity = -5.0;
-            }
+        }
 
-            self.fall_ctn += 1;
-            self.velocity.y += gravity_velocity;
+        let velocity = match world.get_velocity(id) {
+            Some(velocity) => *velocity,
+            None => return
+        };
 
-            if self.velocity.x + self.shape.pos.x < 0.0 {
-                self.velocity.x = 0.0;
-            }
+        if velocity.y == 0.0 {
+            self.can_jump = true;
+        } else if velocity.y < 0.0 {
+            self.can_jump = false;
+        }
 
-            self.check_collision_w_mblocks(mblocks);
-            self.check_collision_w_static(tiles);
-            self.check_collision_w_enemy(goombas);
+        if velocity.x == 0.0 && velocity.y == 0.0 {
+            self.change_sprite_status(id,world,PStatus::Idle)
+        } else if velocity.x != 0.0 && velocity.y == 0.0 {
+            self.change_sprite_status(id,world,PStatus::Walk);
+        } else if velocity.y != 0.0 {
+            self.change_sprite_status(id,world,PStatus::Jump);
+        }
 
+    }
 
-            if self.velocity.y == 0.0 {
-                self.can_jump = true;
-            } else if self.velocity.y < 0.0 {
-                self.can_jump = false;
-            }
 
-            self.shape.pos += self.velocity;
+    fn change_sprite_status(&mut self,id:EntityId,world:&mut World,status:PStatus) {
 
-            if self.status != PStatus::Dead {
-                if self.velocity.x == 0.0 && self.velocity.y == 0.0 {
-                    self.change_sprite_status(PStatus::Idle)
-                } else if self.velocity.x != 0.0 && self.velocity.y == 0.0 {
-                    self.change_sprite_status(PStatus::Walk);
-                } else if self.velocity.y != 0.0 {
-                    self.change_sprite_status(PStatus::Jump);
-                }
+        if self.status != status {
+            self.status = status;
 
-            }
+            let name = match self.status {
+                PStatus::Walk => "walk",
+                PStatus::Idle => "idle",
+                PStatus::Jump => "jump",
+                PStatus::Dead => "dead"
+            };
 
+            if let Some(sprite) = world.get_sprite_mut(id) {
+                sprite.play(name);
+            }
 
         }
-
-        self.update_sprite().unwrap();
-
-
-
     }
 
-}
-
 
-impl SpriteUser for Player {
-    fn reload_sprite(&mut self) {
-        let frame = self.spritesheet.get_current_frame().unwrap();
-        self.draw_info.flip_x = self.spritesheet.should_flip();
-        self.draw_info.source = Some(r::new(
-            frame.position.x,
-            frame.position.y,
-            frame.size.x,
-            frame.size.y
-        ));
-    }
-}
\ No newline at end of file
+}
@@ -0,0 +1,140 @@
+
+use lib_game::GResult;
+
+use macroquad::prelude::{KeyCode, is_key_down, is_key_pressed};
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+
+
+/// what the player asked for during one frame, only the gameplay keys are recorded
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default,Deserialize,Serialize)]
+#[serde(default)]
+pub struct FrameInput {
+    #[serde(skip_serializing_if = "is_false")]
+    pub left:   bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub right:  bool,
+    /// only used to fly with noclip
+    #[serde(skip_serializing_if = "is_false")]
+    pub up:     bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub down:   bool,
+    /// the jump key was pressed this frame
+    #[serde(skip_serializing_if = "is_false")]
+    pub jump:   bool,
+    /// the fire key was pressed this frame
+    #[serde(skip_serializing_if = "is_false")]
+    pub fire:   bool
+}
+
+fn is_false(value:&bool) -> bool { !value }
+
+impl FrameInput {
+
+    /// keep the last held keys, and a jump or a throw until a tick use it
+    pub fn latch(&mut self,input:FrameInput) {
+        self.left = input.left;
+        self.right = input.right;
+        self.up = input.up;
+        self.down = input.down;
+        self.jump |= input.jump;
+        self.fire |= input.fire;
+    }
+
+    pub fn consume(&mut self) {
+        self.jump = false;
+        self.fire = false;
+    }
+
+    pub fn from_keyboard() -> Self {
+        Self {
+            left: is_key_down(KeyCode::A),
+            right: is_key_down(KeyCode::D),
+            up: is_key_down(KeyCode::W),
+            down: is_key_down(KeyCode::S),
+            jump: is_key_pressed(KeyCode::Space),
+            fire: is_key_pressed(KeyCode::J)
+        }
+    }
+
+}
+
+
+/// inputs of every frame of a game, played again from the same level and seed they give the same game
+#[derive(Debug,Clone,PartialEq,Deserialize,Serialize)]
+pub struct Replay {
+    pub level:  String,
+    pub seed:   u64,
+    pub frames: Vec<FrameInput>
+}
+
+impl Replay {
+
+    pub fn new(level:&str,seed:u64) -> Self {
+        Self {
+            level: level.to_string(),
+            seed,
+            frames: Vec::new()
+        }
+    }
+
+    pub fn load(path:&str) -> GResult<Self> {
+
+        let content = fs::read_to_string(path)?;
+
+        match serde_json::from_str(&content) {
+            Ok(replay) => Ok(replay),
+            Err(e) => bail!("invalid replay file '{}': {}",path,e)
+        }
+
+    }
+
+    pub fn save(&self,path:&str) -> GResult<()> {
+
+        let content = match serde_json::to_string(self) {
+            Ok(content) => content,
+            Err(e) => bail!("can't write replay file '{}': {}",path,e)
+        };
+
+        fs::write(path,content + "\n")?;
+
+        Ok(())
+
+    }
+
+}
+
+
+/// where the gameplay inputs come from
+pub enum InputSource {
+    Keyboard,
+    /// the frames of a replay, and the next one to play
+    Replay(Vec<FrameInput>,usize)
+}
+
+impl InputSource {
+
+    pub fn is_replay(&self) -> bool { matches!(self,InputSource::Replay(..)) }
+
+    /// input of the current tick, the keyboard take back the control at the end of a replay
+    pub fn next(&mut self,keyboard:FrameInput) -> FrameInput {
+
+        if let InputSource::Replay(frames,pos) = self {
+
+            if let Some(input) = frames.get(*pos) {
+                *pos += 1;
+                return *input;
+            }
+
+            println!("end of the replay after {} frames",pos);
+            *self = InputSource::Keyboard;
+
+        }
+
+        keyboard
+
+    }
+
+}
@@ -0,0 +1,368 @@
+
+use crate::audio::AudioSettings;
+use crate::player::Power;
+
+use lib_game::GResult;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+
+/// version written in the save file, increase it when the saved data change and add a migration
+pub const SAVE_VERSION: u32 = 1;
+
+const SAVE_NAME: &str = "save.json";
+const GAME_DIR: &str = "rusty-mario";
+
+
+/// everything kept from one run to the next
+#[derive(Debug,Clone,PartialEq,Deserialize,Serialize)]
+#[serde(default)]
+pub struct SaveData {
+    /// levels the player can start from
+    pub unlocked:       BTreeSet<String>,
+    /// best score of each level
+    pub high_scores:    BTreeMap<String,u32>,
+    pub lives:          u32,
+    pub power:          Power,
+    pub settings:       AudioSettings
+}
+
+impl Default for SaveData {
+
+    fn default() -> Self {
+        Self {
+            unlocked: BTreeSet::new(),
+            high_scores: BTreeMap::new(),
+            lives: 0,
+            power: Power::Small,
+            settings: AudioSettings::default()
+        }
+    }
+
+}
+
+
+/// the data with the version of the game that wrote it
+#[derive(Deserialize,Serialize)]
+struct SaveFile {
+    version:    u32,
+    data:       SaveData
+}
+
+
+/// where the save and the quick saves are written, like ~/.local/share/rusty-mario on linux
+pub fn get_data_dir() -> Option<PathBuf> {
+
+    let home = env::var_os("HOME").map(PathBuf::from);
+
+    let base = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home.map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME").map(PathBuf::from)
+            .or(home.map(|home| home.join(".local").join("share")))
+    };
+
+    base.map(|base| base.join(GAME_DIR))
+
+}
+
+/// bring the data of an older save to the current version
+fn migrate(version:u32,data:Value) -> GResult<Value> {
+
+    match version {
+        SAVE_VERSION => Ok(data),
+        version if version > SAVE_VERSION => bail!("the save was written by a newer version of the game ({})",version),
+        version => bail!("no migration from the save version {}",version)
+    }
+
+}
+
+/// order of the levels, the numbers of the name are compared as numbers so lvl-1-10 come after lvl-1-2
+fn get_level_order(name:&str) -> Vec<Result<u32,&str>> {
+    name.split('-').map(|part| part.parse::<u32>().map_err(|_| part)).collect()
+}
+
+fn parse(content:&str) -> GResult<SaveData> {
+
+    let file: Value = match serde_json::from_str(content) {
+        Ok(file) => file,
+        Err(e) => bail!("invalid json: {}",e)
+    };
+
+    let version = match file.get("version").and_then(Value::as_u64) {
+        Some(version) => version as u32,
+        None => bail!("no version")
+    };
+
+    let data = migrate(version,file.get("data").cloned().unwrap_or(Value::Null))?;
+
+    match serde_json::from_value(data) {
+        Ok(data) => Ok(data),
+        Err(e) => bail!("invalid data: {}",e)
+    }
+
+}
+
+
+/// progress of the player, read when the game start and written when it change
+pub struct SaveGame {
+    /// none when the progress must not be written, like during a replay
+    path:   Option<PathBuf>,
+    data:   SaveData
+}
+
+impl SaveGame {
+
+    /// read the save of the user, a missing or broken save start a new one
+    pub fn load() -> Self {
+
+        let path = match get_data_dir() {
+            Some(dir) => dir.join(SAVE_NAME),
+            None => {
+                eprintln!("no data directory found, the progress won't be saved");
+                return Self::disabled();
+            }
+        };
+
+        let data = Self::read(&path).unwrap_or_default();
+
+        Self { path: Some(path), data }
+
+    }
+
+    /// default progress that is never written
+    pub fn disabled() -> Self {
+        Self { path: None, data: SaveData::default() }
+    }
+
+    /// the save, or its backup when it is broken, or nothing
+    fn read(path:&Path) -> Option<SaveData> {
+
+        let backup = path.with_extension("json.bak");
+
+        for file in [path,backup.as_path()] {
+
+            let content = match fs::read_to_string(file) {
+                Ok(content) => content,
+                Err(_) => continue
+            };
+
+            match parse(&content) {
+                Ok(data) => return Some(data),
+                Err(e) => {
+                    // keep the broken file so it can be looked at, it would be replaced by the next save
+                    let corrupt = file.with_extension("corrupt");
+                    eprintln!("the save '{}' is broken ({}), it is moved to '{}'",file.display(),e,corrupt.display());
+                    let _ = fs::rename(file,&corrupt);
+                }
+            }
+
+        }
+
+        None
+
+    }
+
+    /// write the save next to the previous one then replace it, the previous one become the backup
+    pub fn write(&self) -> GResult<()> {
+
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(())
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = SaveFile { version: SAVE_VERSION, data: self.data.clone() };
+
+        let content = match serde_json::to_string_pretty(&file) {
+            Ok(content) => content,
+            Err(e) => bail!("can't write the save: {}",e)
+        };
+
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary,content + "\n")?;
+
+        if path.exists() {
+            fs::rename(path,path.with_extension("json.bak"))?;
+        }
+
+        fs::rename(&temporary,path)?;
+
+        Ok(())
+
+    }
+
+    pub fn get_data(&self) -> &SaveData { &self.data }
+
+    /// the furthest level the player unlocked
+    pub fn get_last_unlocked(&self) -> Option<&str> {
+        self.data.unlocked.iter()
+            .max_by(|a,b| get_level_order(a).cmp(&get_level_order(b)))
+            .map(String::as_str)
+    }
+
+    pub fn unlock(&mut self,level:&str) {
+        self.data.unlocked.insert(level.to_string());
+    }
+
+    /// keep the score if it is the best of the level, return true when it is
+    pub fn record_score(&mut self,level:&str,score:u32) -> bool {
+
+        let best = self.data.high_scores.entry(level.to_string()).or_insert(0);
+
+        if score > *best {
+            *best = score;
+            true
+        } else {
+            false
+        }
+
+    }
+
+    pub fn get_high_score(&self,level:&str) -> u32 {
+        self.data.high_scores.get(level).copied().unwrap_or(0)
+    }
+
+    pub fn set_player(&mut self,lives:u32,power:Power) {
+        self.data.lives = lives;
+        self.data.power = power;
+    }
+
+    pub fn set_settings(&mut self,settings:AudioSettings) {
+        self.data.settings = settings;
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// an empty directory only for this test
+    fn get_test_dir(name:&str) -> PathBuf {
+
+        let dir = env::temp_dir().join(format!("{}-{}-{}",GAME_DIR,name,std::process::id()));
+
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+
+    }
+
+    fn get_content(version:u32,data:&SaveData) -> String {
+        serde_json::to_string(&SaveFile { version, data: data.clone() }).unwrap()
+    }
+
+    #[test]
+    fn last_unlocked_compare_the_level_numbers() {
+
+        let mut save = SaveGame::disabled();
+
+        for level in ["lvl-1-1","lvl-1-2","lvl-1-10","lvl-1-9"] {
+            save.unlock(level);
+        }
+
+        assert_eq!(save.get_last_unlocked(),Some("lvl-1-10"));
+
+        save.unlock("lvl-2-1");
+
+        assert_eq!(save.get_last_unlocked(),Some("lvl-2-1"));
+
+    }
+
+    #[test]
+    fn parse_the_current_version() {
+
+        let mut data = SaveData::default();
+        data.unlocked.insert("lvl-1-2".to_string());
+        data.lives = 4;
+
+        assert_eq!(parse(&get_content(SAVE_VERSION,&data)).unwrap(),data);
+
+    }
+
+    #[test]
+    fn parse_refuse_a_newer_version() {
+        let error = parse(&get_content(SAVE_VERSION + 1,&SaveData::default())).unwrap_err();
+        assert!(error.to_string().contains("newer"),"{}",error);
+    }
+
+    #[test]
+    fn parse_refuse_a_save_without_version() {
+        let error = parse(r#"{ "data": { "lives": 3 } }"#).unwrap_err();
+        assert!(error.to_string().contains("no version"),"{}",error);
+    }
+
+    #[test]
+    fn parse_refuse_invalid_data() {
+
+        let error = parse(&format!(r#"{{ "version": {}, "data": {{ "lives": "three" }} }}"#,SAVE_VERSION)).unwrap_err();
+        assert!(error.to_string().contains("invalid data"),"{}",error);
+
+        let error = parse("{ not json").unwrap_err();
+        assert!(error.to_string().contains("invalid json"),"{}",error);
+
+    }
+
+    #[test]
+    fn migrate_keep_the_current_version_and_refuse_the_others() {
+
+        let data = serde_json::json!({ "lives": 2 });
+
+        assert_eq!(migrate(SAVE_VERSION,data.clone()).unwrap(),data);
+        assert!(migrate(SAVE_VERSION + 1,data.clone()).is_err());
+        assert!(migrate(0,data).is_err());
+
+    }
+
+    #[test]
+    fn read_fall_back_to_the_backup_and_keep_the_broken_save() {
+
+        let dir = get_test_dir("read-backup");
+        let path = dir.join(SAVE_NAME);
+
+        let data = SaveData { lives: 7, ..SaveData::default() };
+
+        fs::write(&path,"{ broken").unwrap();
+        fs::write(path.with_extension("json.bak"),get_content(SAVE_VERSION,&data)).unwrap();
+
+        assert_eq!(SaveGame::read(&path),Some(data));
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(path.with_extension("corrupt")).unwrap(),"{ broken");
+
+        let _ = fs::remove_dir_all(&dir);
+
+    }
+
+    #[test]
+    fn read_nothing_when_the_backup_is_broken_too() {
+
+        let dir = get_test_dir("read-broken");
+        let path = dir.join(SAVE_NAME);
+
+        fs::write(&path,"{ broken").unwrap();
+        fs::write(path.with_extension("json.bak"),"{ broken too").unwrap();
+
+        assert_eq!(SaveGame::read(&path),None);
+        assert!(path.with_extension("corrupt").exists());
+        assert!(path.with_extension("json.corrupt").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+
+    }
+
+}
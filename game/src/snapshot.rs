@@ -0,0 +1,217 @@
+
+use crate::animation::AnimationState;
+use crate::collision::{Contact, SolidKind};
+use crate::component::Collider;
+use crate::level::{Checkpoint, Point};
+use crate::state::GameStatus;
+use crate::timer::LevelTimer;
+use crate::world::{BehaviourState, EntityId, World};
+
+use lib_game::GResult;
+use lib_game::vector::Vec2;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::VecDeque;
+use std::fs;
+use std::mem::discriminant;
+
+
+/// what is shown of an entity
+#[derive(Debug,Clone,PartialEq,Deserialize,Serialize)]
+pub struct SpriteSnapshot {
+    pub animation:      AnimationState,
+    pub visible:        bool,
+    pub flipped:        bool,
+    #[serde(default)]
+    pub upside_down:    bool
+}
+
+/// every component of an entity at one tick
+#[derive(Debug,Clone,PartialEq,Deserialize,Serialize)]
+pub struct EntitySnapshot {
+    pub position:   Option<Point>,
+    pub velocity:   Option<Point>,
+    pub collider:   Option<Collider>,
+    pub previous:   Point,
+    pub active:     bool,
+    /// entity the body stood on, a moving one carry it on the next tick
+    #[serde(default)]
+    pub ground:     Option<EntityId>,
+    pub sprite:     Option<SpriteSnapshot>,
+    pub behaviour:  Option<BehaviourState>
+}
+
+/// the whole simulation at one tick, the map and the level data don't change while playing so they are left out
+#[derive(Debug,Clone,PartialEq,Deserialize,Serialize)]
+pub struct Snapshot {
+    pub level:      String,
+    pub frame:      u64,
+    pub status:     GameStatus,
+    pub status_ctn: u32,
+    pub lives:      u32,
+    pub score:      u32,
+    /// missing in the snapshots written before the coins existed
+    #[serde(default)]
+    pub coins:      u32,
+    pub checkpoint: Option<Checkpoint>,
+    pub timer:      LevelTimer,
+    /// offset of the camera
+    pub camera:     Point,
+    pub entities:   Vec<EntitySnapshot>
+}
+
+impl Snapshot {
+
+    pub fn load(path:&str) -> GResult<Self> {
+
+        let content = fs::read_to_string(path)?;
+
+        match serde_json::from_str(&content) {
+            Ok(snapshot) => Ok(snapshot),
+            Err(e) => bail!("invalid snapshot file '{}': {}",path,e)
+        }
+
+    }
+
+    pub fn save(&self,path:&str) -> GResult<()> {
+
+        let content = match serde_json::to_string(self) {
+            Ok(content) => content,
+            Err(e) => bail!("can't write snapshot file '{}': {}",path,e)
+        };
+
+        fs::write(path,content + "\n")?;
+
+        Ok(())
+
+    }
+
+}
+
+
+fn to_point(vec:&Vec2) -> Point { Point { x: vec.x, y: vec.y } }
+fn to_vec(point:&Point) -> Vec2 { Vec2::new(point.x,point.y) }
+
+
+/// components of every entity of the world
+pub fn capture_world(world:&World) -> Vec<EntitySnapshot> {
+
+    world.entities().map(|id| EntitySnapshot {
+        position: world.positions[id].as_ref().map(to_point),
+        velocity: world.velocities[id].as_ref().map(to_point),
+        collider: world.colliders[id],
+        previous: to_point(&world.previous[id]),
+        active: world.active[id],
+        ground: world.contacts[id].iter()
+            .filter(|contact| contact.is_ground())
+            .find_map(|contact| match contact.kind {
+                SolidKind::Entity(ground) => Some(ground),
+                SolidKind::Tile => None
+            }),
+        sprite: world.sprites[id].as_ref().map(|sprite| SpriteSnapshot {
+            animation: sprite.get_animation(),
+            visible: sprite.visible,
+            flipped: sprite.is_flipped(),
+            upside_down: sprite.is_upside_down()
+        }),
+        behaviour: world.behaviours[id].as_ref().map(|behaviour| behaviour.get_state())
+    }).collect()
+
+}
+
+/// put back the components of every entity, the world must have the same entities as when it was captured
+pub fn restore_world(world:&mut World,entities:&[EntitySnapshot]) -> GResult<()> {
+
+    if entities.len() != world.entities().len() {
+        bail!("the snapshot has {} entities and the world {}",entities.len(),world.entities().len());
+    }
+
+    // nothing is changed if one of the entities doesn't match, a behaviour only refuse a state of another kind
+    for (id,entity) in entities.iter().enumerate() {
+
+        let same = match (&world.behaviours[id],&entity.behaviour) {
+            (Some(behaviour),Some(state)) => discriminant(&behaviour.get_state()) == discriminant(state),
+            (None,None) => true,
+            _ => false
+        };
+
+        let ground_exists = entity.ground.is_none_or(|ground| ground < entities.len());
+
+        if !same || !ground_exists || world.sprites[id].is_some() != entity.sprite.is_some() {
+            bail!("the entity {} is not the one of the snapshot",id);
+        }
+
+        if let (Some(sprite),Some(state)) = (world.sprites[id].as_ref(),&entity.sprite) {
+            if let Err(e) = sprite.check_animation(&state.animation) {
+                bail!("the sprite of the entity {} can't be restored: {}",id,e);
+            }
+        }
+
+    }
+
+    for (id,entity) in entities.iter().enumerate() {
+
+        world.positions[id] = entity.position.as_ref().map(to_vec);
+        world.velocities[id] = entity.velocity.as_ref().map(to_vec);
+        world.colliders[id] = entity.collider;
+        world.previous[id] = to_vec(&entity.previous);
+        world.active[id] = entity.active;
+        world.contacts[id] = entity.ground.iter()
+            .map(|ground| Contact { kind: SolidKind::Entity(*ground), normal: Vec2::new(0.0,1.0) })
+            .collect();
+
+        if let (Some(sprite),Some(state)) = (world.sprites[id].as_mut(),&entity.sprite) {
+            sprite.visible = state.visible;
+            sprite.flip(state.flipped);
+            sprite.set_upside_down(state.upside_down);
+            sprite.set_animation(&state.animation)?;
+        }
+
+        if let (Some(behaviour),Some(state)) = (world.behaviours[id].as_mut(),&entity.behaviour) {
+            behaviour.set_state(state)?;
+        }
+
+    }
+
+    // the sounds belong to the ticks that are undone
+    world.take_sounds();
+
+    Ok(())
+
+}
+
+
+/// the last snapshots of the game, the oldest is forgotten when it is full
+pub struct RewindBuffer {
+    snapshots:  VecDeque<Snapshot>,
+    capacity:   usize
+}
+
+impl RewindBuffer {
+
+    pub fn new(capacity:usize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity
+        }
+    }
+
+    pub fn push(&mut self,snapshot:Snapshot) {
+
+        if self.snapshots.len() >= self.capacity {
+            self.snapshots.pop_front();
+        }
+
+        self.snapshots.push_back(snapshot);
+
+    }
+
+    /// the most recent snapshot, it is removed so the next one is older
+    pub fn pop(&mut self) -> Option<Snapshot> { self.snapshots.pop_back() }
+
+    pub fn clear(&mut self) { self.snapshots.clear(); }
+
+    pub fn len(&self) -> usize { self.snapshots.len() }
+
+}
@@ -1,294 +1,1440 @@
-
 use lib_game::GResult;
-use lib_game::vector::Vec2;
 use lib_game::Direction;
+use lib_game::vector::Vec2;
 
 
-use crate::entity::{Dynamic, Entity, Goomba, MysteryBlocks};
-use crate::player::Player;
-use crate::map::Map;
+use crate::audio::{AudioManager, SoundEffect};
+use crate::console::{Command, Console, SpawnKind};
+use crate::cli::{DEFAULT_LEVEL, Options};
+use crate::debug::DebugOverlay;
+use crate::editor::Editor;
+use crate::entity::{Coin, Goomba, MysteryBlocks, get_view};
+use crate::player::{Player, Power};
+use crate::level::{BlockKind, Checkpoint, Depth, LevelData, Point};
+use crate::map::{Map, get_next_level, level_exists};
+use crate::physics::{PhysicsProfile, PhysicsWatcher};
+use crate::platform::Platform;
+use crate::event::{ContactEvent, collect_contact_events};
+use crate::fireball::{Fireball, MAX_FIREBALLS};
+use crate::world::{Behaviour, EntityId, Pickup, World};
+use crate::system;
+use crate::timer::LevelTimer;
+use crate::save::{SaveGame, get_data_dir};
+use crate::snapshot::{RewindBuffer, Snapshot, capture_world, restore_world};
+use crate::replay::{FrameInput, InputSource, Replay};
 
 use macroquad::prelude::*;
 
-use crate::declaration::{ASSETS_DIR, HEIGHT, WIDTH};
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::assets::get_assets_dir;
+use crate::declaration::{HEIGHT, TILE_SIZE, WIDTH};
+
+
+/// camera offset at the start of the level
+pub const CAMERA_OFFSET: (f32,f32) = (1.4,1.25);
+
+/// number of life the player start with
+const START_LIVES: u32 = 3;
+/// number of frame the world stay frozen while the player die
+const DEATH_DURATION: u32 = 180;
+/// number of frame the level name and the lives stay on screen
+const INTERSTITIAL_DURATION: u32 = 150;
+const GAME_OVER_DURATION: u32 = 240;
 
-const PLAYER_VELOCITY: f32 = 2.0;
+/// points given for each enemy stomped
+const STOMP_SCORE: u32 = 100;
+/// points given at the end of the level for each unit of time left
+const TIME_SCORE: u32 = 50;
+/// points given for each coin
+const COIN_SCORE: u32 = 200;
+/// number of coin that give one more life
+const LIFE_COINS: u32 = 100;
+/// points given when the player take a star or a fire flower
+const POWER_UP_SCORE: u32 = 1000;
 
+/// speeds chosen by the slow motion key, in order
+const SLOW_MOTION_SCALES: [f32; 4] = [1.0,0.5,0.25,0.1];
 
-#[derive(PartialEq)]
+/// number of tick the game can go back in time
+const REWIND_TICKS: usize = 5 * 60;
+
+
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
 pub enum GameStatus {
 
     GamePlay,
+    /// the world is frozen while the death animation play
+    Dying,
+    /// black screen with the level name and the remaining lives
+    Interstitial,
+    GameOver,
     Win
 
 }
 
 
+/// create every entity of the level, return the world and the player id
+async fn spawn_world(level:&LevelData,physics:&PhysicsProfile) -> GResult<(World,EntityId)> {
+
+    let mut world = World::new();
+
+    let player = Player::spawn(&mut world,Vec2::new(level.player.x,level.player.y),physics).await?;
+
+    for goomba in level.goombas.iter() {
+        Goomba::spawn(&mut world,goomba.x,goomba.y,goomba.direction.get_direction(),goomba.wake_x).await?;
+    }
+
+    for block in level.blocks.iter() {
+        MysteryBlocks::spawn(&mut world,block.x,block.y,block.kind).await?;
+    }
+
+    for coin in level.coins.iter() {
+        Coin::spawn(&mut world,coin.x,coin.y).await?;
+    }
+
+    for platform in level.platforms.iter() {
+        Platform::spawn(&mut world,platform.x,platform.y,platform.width,platform.motion).await?;
+    }
+
+    // the fireballs of the player are thrown again and again instead of being spawned
+    for _ in 0..MAX_FIREBALLS {
+        Fireball::spawn(&mut world).await?;
+    }
+
+    Ok((world,player))
+
+}
+
+
 pub struct State {
     camera:             Camera2D,
     game_status:        GameStatus,
-    player:             Player,
+    /// number of frame since the game status changed
+    status_ctn:         u32,
+    lives:              u32,
+    /// points of the current game, reset on game over
+    score:              u32,
+    /// coins taken since the last life they gave
+    coins:              u32,
+    world:              World,
+    player:             EntityId,
     map:                Map,
-    goombas:            Vec<Goomba>,
-    mystery_blocks:     Vec<MysteryBlocks>,
-    win_message:        Texture2D
+    level:              LevelData,
+    /// last checkpoint the player passed
+    checkpoint:         Option<Checkpoint>,
+    physics:            PhysicsWatcher,
+    audio:              AudioManager,
+    timer:              LevelTimer,
+    editor:             Editor,
+    /// the entities of the level changed in the editor, the world must be spawned again
+    world_outdated:     bool,
+    win_message:        Texture2D,
+    input:              InputSource,
+    /// inputs of every frame, written to the path when the game close
+    recording:          Option<(String,Replay)>,
+    /// number of frame since the game started
+    frame:              u64,
+    debug:              DebugOverlay,
+    console:            Console,
+    /// entities asked by the console, they are spawned with the next sync of the world
+    spawns:             Vec<(SpawnKind,f32,f32)>,
+    /// level asked by the console, loaded with the next sync of the world
+    next_level:         Option<String>,
+    /// number of tick for each frame, under 1 some frames don't move the world
+    time_scale:         f32,
+    /// part of tick not done yet
+    time_acc:           f32,
+    /// keys pressed since the last tick
+    keyboard:           FrameInput,
+    /// the world only move by the steps asked with the step key
+    paused:             bool,
+    steps:              u32,
+    save:               SaveGame,
+    /// one snapshot by tick, to go back in time
+    rewind:             RewindBuffer,
+    /// the rewind key is held, the game go back one tick by frame
+    rewinding:          bool
 }
 
 impl State {
 
-    pub async fn init() -> GResult<Self> {
+    pub async fn init(options:&Options) -> GResult<Self> {
+
+        let replay = match &options.replay {
+            Some(path) => Some(Replay::load(path)?),
+            None => None
+        };
+
+        // a replay or a headless run must play the same way on every computer, it ignore the progress
+        let mut save = if replay.is_some() || options.headless.is_some() { SaveGame::disabled() } else { SaveGame::load() };
+        save.unlock(DEFAULT_LEVEL);
+
+        // a replay is only the same game with its own level and seed, else the game continue from the last level unlocked
+        let level_name = options.level.as_deref()
+            .or(replay.as_ref().map(|replay| replay.level.as_str()))
+            .or(save.get_last_unlocked().filter(|level| level_exists(level)))
+            .unwrap_or(DEFAULT_LEVEL)
+            .to_string();
+
+        let seed = match (&replay,options.seed) {
+            (Some(replay),Some(seed)) if seed != replay.seed => {
+                eprintln!("the replay was recorded with the seed {}, the seed {} is ignored",replay.seed,seed);
+                replay.seed
+            },
+            (Some(replay),_) => replay.seed,
+            (None,Some(seed)) => seed,
+            (None,None) => SystemTime::now().duration_since(UNIX_EPOCH).map_or(0,|time| time.as_secs())
+        };
+
+        // nothing is random yet, the seed is only kept in the replay for when something will be
+        rand::srand(seed);
 
         let camera = Camera2D {
             zoom: vec2(2.4,2.4),
-            offset: vec2(1.4,1.25),
+            offset: vec2(CAMERA_OFFSET.0,CAMERA_OFFSET.1),
             ..Default::default()
         };
 
-        let mut map = Map::init()?;
+        let mut map = Map::init(&level_name)?;
         map.load().await?;
 
-        let player = Player::new(Vec2{ x: 80.0, y: 48.0 }).await?;
-
-        let mystery_blocks = vec![
-            MysteryBlocks::new(256.0,96.0).await?,
-            MysteryBlocks::new(336.0,96.0).await?,
-            MysteryBlocks::new(352.0,160.0).await?,
-            MysteryBlocks::new(368.0,96.0).await?,
-            MysteryBlocks::new(1024.0,112.0).await?,
-            MysteryBlocks::new(1280.0,96.0).await?,
-            MysteryBlocks::new(1536.0,160.0).await?,
-            MysteryBlocks::new(1536.0,96.0).await?,
-            MysteryBlocks::new(1648.0,96.0).await?,
-            MysteryBlocks::new(1728.0,96.0).await?,
-            MysteryBlocks::new(1776.0,160.0).await?,
-            MysteryBlocks::new(1776.0,96.0).await?,
-            MysteryBlocks::new(1824.0,96.0).await?,
-            MysteryBlocks::new(2096.0,160.0).await?,
-            MysteryBlocks::new(2112.0,160.0).await?,
-            MysteryBlocks::new(2752.0,96.0).await?,
-
-        ];
-
-        let goombas = vec![
-
-            Goomba::new(352.0,48.0,Direction::Right,false).await?,
-            Goomba::new(640.0,48.0,Direction::Left, false).await?,
-            Goomba::new(848.0,48.0,Direction::Left,false).await?,
-            Goomba::new(880.0,48.0,Direction::Left,false).await?,
-            // start on the platform
-            Goomba::new(1312.0,176.0,Direction::Left,true).await?,
-            Goomba::new(1344.0,176.0,Direction::Left,true).await?,
-            // after the platform
-            Goomba::new(1552.0,48.0,Direction::Left,true).await?,
-            Goomba::new(1584.0,48.0,Direction::Left,true).await?,
-
-            Goomba::new(2085.0,48.0,Direction::Right,true).await?,
-            Goomba::new(2064.0,48.0,Direction::Right,true).await?,
-            Goomba::new(2040.0,48.0,Direction::Left,true).await?,
-            Goomba::new(2016.0,48.0,Direction::Left,true).await?,
-            Goomba::new(2752.0,48.0,Direction::Left,false).await?,
-            Goomba::new(2768.0,48.0,Direction::Right,false).await?
-
-        ];
+        let physics = PhysicsWatcher::init()?;
+
+        let mut audio = AudioManager::init(map.get_name()).await;
+        audio.set_settings(save.get_data().settings);
+
+        // nobody listen to a game that is not drawn
+        if options.headless.is_some() {
+            audio.set_muted(true);
+        }
+
+        let level = map.load_level_data()?;
+
+        let (mut world,player) = spawn_world(&level,physics.get_profile()).await?;
+
+        // the player come back as he was when the game was closed
+        let power = save.get_data().power;
+        world.with_behaviour(player,|behaviour,world| {
+            if let Some(p) = behaviour.as_player_mut() {
+                p.set_power(player,world,power);
+            }
+        });
+
+        let lives = match save.get_data().lives {
+            0 => START_LIVES,
+            lives => lives
+        };
+
+        let editor = Editor::new(&map);
 
         // message when you finish the level
-        let p_win_msg = format!("{}/win-message.png",ASSETS_DIR);
+        let p_win_msg = format!("{}/win-message.png",get_assets_dir());
         let win_message = load_texture(&p_win_msg).await?;
 
         set_camera(&camera);
 
         Ok(Self {
             camera,
-            game_status: GameStatus::GamePlay,
+            // the level name is shown before the first try too
+            game_status: GameStatus::Interstitial,
+            status_ctn: 0,
+            lives,
+            score: 0,
+            coins: 0,
+            world,
             player,
             map,
-            goombas,
-            mystery_blocks,
-            win_message
+            level,
+            checkpoint: None,
+            physics,
+            audio,
+            timer: LevelTimer::new(),
+            editor,
+            world_outdated: false,
+            win_message,
+            input: match replay {
+                Some(replay) => InputSource::Replay(replay.frames,0),
+                None => InputSource::Keyboard
+            },
+            recording: options.record.as_ref().map(|path| (path.clone(),Replay::new(&level_name,seed))),
+            frame: 0,
+            debug: DebugOverlay::new(options.debug),
+            console: Console::new(),
+            spawns: Vec::new(),
+            next_level: None,
+            time_scale: 1.0,
+            time_acc: 0.0,
+            keyboard: FrameInput::default(),
+            paused: false,
+            steps: 0,
+            save,
+            rewind: RewindBuffer::new(REWIND_TICKS),
+            rewinding: false
         })
 
     }
 
 
 
+    /// read the keyboard once by frame, the gameplay keys wait for the next tick
     pub fn handle_input(&mut self) {
 
-        self.player.clear_velocity();
+        if self.rewinding && (!is_key_down(KeyCode::R) || self.console.is_open()) {
+            self.rewinding = false;
+            self.resume_music();
+        }
+
+        // the keys typed in the console don't move the player
+        if !self.console.is_open() {
+            self.keyboard.latch(FrameInput::from_keyboard());
+        }
+
+        if is_key_pressed(KeyCode::GraveAccent) {
+            self.console.toggle();
+        }
+
+        if let Some(line) = self.console.handle_input() {
+            self.run_command(&line);
+        }
+
+        if !self.console.is_open() {
+            self.handle_hotkeys();
+        }
+
+        if self.editor.is_active() && !self.console.is_open() {
+            self.editor.handle_input(&mut self.camera,&mut self.map,&mut self.level);
+        }
+
+    }
+
+    /// move the player with the input of this tick
+    fn apply_input(&mut self,input:FrameInput) {
+
+        let player = self.player;
+        let player_velocity = self.physics.get_profile().player_velocity;
 
+        let mut velocity = Vec2::new(0.0,0.0);
 
         if self.game_status == GameStatus::GamePlay {
 
-            if is_key_down(KeyCode::A)  {
-                self.player.set_xvelocity(-PLAYER_VELOCITY);
-                self.player.flip_spritesheet(true);
+            if input.left {
+                velocity.x -= player_velocity;
+                self.flip_player(true);
+            }
+
+            if input.right {
+                velocity.x += player_velocity;
+                self.flip_player(false);
             }
 
-            if is_key_down(KeyCode::D) {
-                self.player.set_xvelocity(PLAYER_VELOCITY);
-                self.player.flip_spritesheet(false);
+            if input.jump {
+                self.world.with_behaviour(player,|behaviour,world| {
+                    if let Some(player) = behaviour.as_player_mut() {
+                        player.jump(world);
+                    }
+                });
             }
 
-            if is_key_pressed(KeyCode::Space)  {
-                self.player.jump();
+            if input.fire {
+                self.throw_fireball();
             }
 
+            // with noclip the player fly where he want
+            if self.get_player().is_some_and(|player| player.has_noclip()) {
+
+                if input.up {
+                    velocity.y += player_velocity;
+                }
+
+                if input.down {
+                    velocity.y -= player_velocity;
+                }
+
+            }
+
+        }
+
+        if let Some(v) = self.world.get_velocity_mut(player) {
+            *v = velocity;
+        }
+
+    }
+
+    /// keys that act on the game itself and not on the player
+    fn handle_hotkeys(&mut self) {
+
+        if is_key_pressed(KeyCode::Tab) {
+            self.toggle_editor();
+        }
+
+        if is_key_pressed(KeyCode::F3) {
+            self.debug.toggle();
+        }
+
+        if is_key_pressed(KeyCode::F5) {
+            self.paused = !self.paused;
+            self.steps = 0;
+        }
+
+        // one more tick, the game pause if it was running
+        if is_key_pressed(KeyCode::F6) {
+            self.paused = true;
+            self.steps += 1;
+        }
+
+        if is_key_pressed(KeyCode::F7) {
+            self.cycle_time_scale();
+        }
+
+        if self.editor.is_active() {
+            return;
         }
 
         if is_key_pressed(KeyCode::U) {
             self.reset();
         }
 
+        if is_key_pressed(KeyCode::F8) {
+            self.quick_save();
+        }
+
+        if is_key_pressed(KeyCode::F9) {
+            self.quick_load();
+        }
+
+        // held to go back in time
+        if is_key_pressed(KeyCode::R) && self.can_go_back() {
+            self.rewinding = true;
+            self.audio.stop_music();
+        }
+
+        if is_key_pressed(KeyCode::M) {
+            self.audio.toggle_mute();
+        }
+
+        if is_key_pressed(KeyCode::Minus) {
+            self.audio.volume_down();
+        }
+
+        if is_key_pressed(KeyCode::Equal) {
+            self.audio.volume_up();
+        }
+
     }
 
-    fn reset(&mut self) {
+    /// write the recorded inputs, called once when the game stop
+    pub fn finish(&mut self) -> GResult<()> {
+
+        if let Some((path,replay)) = &self.recording {
+            replay.save(path)?;
+            println!("replay of {} frames written to '{}'",replay.frames.len(),path);
+        }
+
+        self.write_save();
+
+        Ok(())
+
+    }
+
+    /// keep the lives, the power and the settings, a save that can't be written doesn't stop the game
+    fn write_save(&mut self) {
+
+        let power = self.get_player().map_or(Power::Small,|player| player.get_power());
 
-        self.player.reset();
+        self.save.set_player(self.lives,power);
+        self.save.set_settings(self.audio.get_settings());
 
-        for goomba in self.goombas.iter_mut() {
-            goomba.reset();
+        if let Err(e) = self.save.write() {
+            eprintln!("can't write the save: {}",e);
         }
 
-        for block in self.mystery_blocks.iter_mut() {
-            block.reset();
+    }
+
+    /// the whole simulation as it is now
+    fn take_snapshot(&self) -> Snapshot {
+        Snapshot {
+            level: self.map.get_name().to_string(),
+            frame: self.frame,
+            status: self.game_status,
+            status_ctn: self.status_ctn,
+            lives: self.lives,
+            score: self.score,
+            coins: self.coins,
+            checkpoint: self.checkpoint,
+            timer: self.timer,
+            camera: Point { x: self.camera.offset.x, y: self.camera.offset.y },
+            entities: capture_world(&self.world)
         }
+    }
 
+    /// put the simulation back as it was, the snapshot must come from the same level and entities
+    fn restore_snapshot(&mut self,snapshot:&Snapshot) -> GResult<()> {
 
-        self.game_status = GameStatus::GamePlay;
+        if snapshot.level != self.map.get_name() {
+            bail!("the snapshot is from the level '{}'",snapshot.level);
+        }
 
-        self.camera.offset = vec2(1.4,1.25);
+        restore_world(&mut self.world,&snapshot.entities)?;
+
+        // the size of the sprite follow the power of the player
+        let player = self.player;
+        self.world.with_behaviour(player,|behaviour,world| {
+            if let Some(p) = behaviour.as_player_mut() {
+                let power = p.get_power();
+                p.set_power(player,world,power);
+            }
+        });
+
+        self.frame = snapshot.frame;
+        self.game_status = snapshot.status;
+        self.status_ctn = snapshot.status_ctn;
+        self.lives = snapshot.lives;
+        self.score = snapshot.score;
+        self.coins = snapshot.coins;
+        self.checkpoint = snapshot.checkpoint;
+        self.timer = snapshot.timer;
+
+        self.camera.offset = vec2(snapshot.camera.x,snapshot.camera.y);
         set_camera(&self.camera);
 
+        Ok(())
+
     }
 
-    pub fn update(&mut self) {
+    /// going back in time would make the recorded or replayed inputs wrong
+    fn can_go_back(&mut self) -> bool {
 
-        if self.game_status == GameStatus::GamePlay {
+        if self.recording.is_some() || self.input.is_replay() {
+            self.console.print("the time can't go back while a replay is played or recorded");
+            return false;
+        }
 
-            self.player.update(
-                (self.map.get_tiles(),&mut self.mystery_blocks,&mut self.goombas)
-            );
+        true
+
+    }
+
+    /// one quick save by level, next to the save of the progress
+    fn get_quick_save_path(&self) -> GResult<String> {
+
+        let dir = match get_data_dir() {
+            Some(dir) => dir,
+            None => bail!("no data directory found")
+        };
+
+        fs::create_dir_all(&dir)?;
+
+        Ok(dir.join(format!("quicksave-{}.json",self.map.get_name())).to_string_lossy().into_owned())
+
+    }
+
+    fn quick_save(&mut self) {
+
+        let result = self.get_quick_save_path()
+            .and_then(|path| self.take_snapshot().save(&path).map(|_| path));
+
+        let answer = match result {
+            Ok(path) => format!("state saved to '{}'",path),
+            Err(e) => format!("can't save the state: {}",e)
+        };
+
+        self.console.print(&answer);
+
+    }
+
+    fn quick_load(&mut self) {
+
+        if !self.can_go_back() {
+            return;
+        }
+
+        let result = self.get_quick_save_path()
+            .and_then(|path| Snapshot::load(&path))
+            .and_then(|snapshot| self.restore_snapshot(&snapshot));
+
+        match result {
+            Ok(()) => {
+                // the ticks in the buffer happened after the state that is loaded
+                self.rewind.clear();
+                self.resume_music();
+                self.console.print("state loaded");
+            },
+            Err(e) => self.console.print(&format!("can't load the state: {}",e))
+        }
+
+    }
+
+    /// go back one tick, the game stay on the oldest snapshot once there is no more
+    fn step_back(&mut self) {
+
+        // the jump or the throw pressed while rewinding is forgotten
+        self.keyboard.consume();
+
+        if let Some(snapshot) = self.rewind.pop() {
+            if let Err(e) = self.restore_snapshot(&snapshot) {
+                self.console.print(&format!("can't go back in time: {}",e));
+                self.rewind.clear();
+            }
+        }
+
+    }
+
+    /// play the music that fit the game after the time changed
+    fn resume_music(&mut self) {
+
+        if self.game_status != GameStatus::GamePlay {
+            self.audio.stop_music();
+        } else if self.player_has_star() {
+            self.audio.star_power();
+        } else if self.timer.is_hurry() {
+            self.audio.hurry_up();
+        } else {
+            self.audio.start_music();
+        }
+
+    }
+
+    /// the level is finished, the time left is added to the score and the next level is unlocked
+    fn clear_level(&mut self) {
+
+        self.score += self.timer.get_time() * TIME_SCORE;
+
+        let name = self.map.get_name().to_string();
+
+        if self.save.record_score(&name,self.score) {
+            self.console.print(&format!("new high score {} on '{}'",self.score,name));
+        }
+
+        if let Some(next) = get_next_level(&name) {
+            self.save.unlock(&next);
+        }
+
+        self.write_save();
+
+    }
+
+    /// one line summary of the game, printed at the end of a headless run
+    pub fn describe(&self) -> String {
+
+        let position = self.world.get_position(self.player).map_or(Vec2::new(0.0,0.0),|pos| *pos);
+
+        format!(
+            "frame {}: {:?}, lives {}, player at ({:.1}, {:.1}), time {}",
+            self.frame,
+            self.game_status,
+            self.lives,
+            position.x,
+            position.y,
+            self.timer.get_time()
+        )
+
+    }
+
+    /// the level start again from the beginning when the editor is closed
+    fn toggle_editor(&mut self) {
+
+        self.editor.toggle();
 
+        if self.editor.is_active() {
+            self.audio.stop_music();
+        } else {
+            self.world_outdated = self.editor.take_changed();
+            self.reset();
+        }
+
+    }
 
-            if self.player.get_y() <= 0.0 {
-                self.reset();
+    /// spawn the entities again once they have been changed in the editor
+    pub async fn sync_world(&mut self) -> GResult<()> {
+
+        if let Some(name) = self.next_level.take() {
+            if let Err(e) = self.load_level(&name).await {
+                self.console.print(&format!("can't load the level '{}': {}",name,e));
             }
+        }
 
+        for (kind,x,y) in std::mem::take(&mut self.spawns) {
 
-            if self.player.get_rect().get_max_x() >= 200.0
-                && self.player.get_xvelocity() != 0.0
-                && !self.player.is_dying()
-                && self.player.get_rect().get_max_x() <= 3164.0 {
+            let id = match kind {
+                SpawnKind::Goomba => Goomba::spawn(&mut self.world,x,y,Direction::Left,None).await?,
+                SpawnKind::Block => MysteryBlocks::spawn(&mut self.world,x,y,BlockKind::Mystery).await?
+            };
 
+            // it was asked to be there right now, not when the camera come close
+            self.world.active[id] = true;
 
-                self.camera.offset += vec2(-self.player.get_xvelocity()/(WIDTH/2.0)*2.4,0.0);
-                set_camera(&self.camera);
+            // the snapshots don't have this entity
+            self.rewind.clear();
+
+        }
 
+        if !self.world_outdated {
+            return Ok(());
+        }
+
+        let (world,player) = spawn_world(&self.level,self.physics.get_profile()).await?;
+
+        self.world = world;
+        self.player = player;
+        self.world_outdated = false;
+        self.rewind.clear();
+
+        self.reset();
+
+        Ok(())
+
+    }
 
+    /// replace the level by another one, a level file or a name like 1-2
+    async fn load_level(&mut self,name:&str) -> GResult<()> {
+
+        let name = if name.contains('.') || name.starts_with("lvl-") { name.to_string() } else { format!("lvl-{}",name) };
+
+        let mut map = Map::init(&name)?;
+        map.load().await?;
+
+        let level = map.load_level_data()?;
+        let (world,player) = spawn_world(&level,self.physics.get_profile()).await?;
+
+        self.audio.load_music(map.get_name()).await;
+        self.editor = Editor::new(&map);
+
+        self.map = map;
+        self.level = level;
+        self.world = world;
+        self.player = player;
+        self.world_outdated = false;
+        self.rewind.clear();
+
+        self.reset();
+        self.change_status(GameStatus::Interstitial);
+
+        self.console.print(&format!("level '{}' loaded",self.map.get_name()));
+
+        Ok(())
+
+    }
+
+    /// run a line typed in the console and print its answer
+    fn run_command(&mut self,line:&str) {
+
+        let command = match Command::parse(line) {
+            Ok(command) => command,
+            Err(e) => {
+                self.console.print(&e.to_string());
+                return;
             }
+        };
 
+        let id = self.player;
 
-            for goomba in self.goombas.iter_mut() {
-                if !goomba.is_disappear() {
-                    goomba.update(self.map.get_tiles());
+        let answer = match command {
+            Command::Teleport(x,y) => {
+
+                self.world.with_behaviour(id,|behaviour,world| {
+                    if let Some(player) = behaviour.as_player_mut() {
+                        player.respawn(id,world,Vec2::new(x,y));
+                    }
+                });
+
+                self.move_camera_to(x);
+
+                format!("teleported to {} {}",x,y)
+
+            },
+            Command::Spawn(kind,x,y) => {
+                self.spawns.push((kind,x,y));
+                format!("{:?} spawned at {} {}",kind,x,y)
+            },
+            Command::GodMode => {
+
+                let mut enabled = false;
+
+                if let Some(player) = self.get_player_mut() {
+                    player.toggle_godmode();
+                    enabled = player.has_godmode();
                 }
 
+                format!("godmode {}",if enabled { "on" } else { "off" })
+
+            },
+            Command::NoClip => {
+
+                let mut enabled = false;
+
+                self.world.with_behaviour(id,|behaviour,world| {
+                    if let Some(player) = behaviour.as_player_mut() {
+                        player.toggle_noclip(id,world);
+                        enabled = player.has_noclip();
+                    }
+                });
+
+                format!("noclip {}, fly with W and S",if enabled { "on" } else { "off" })
+
+            },
+            Command::SetPower(power) => {
+
+                self.world.with_behaviour(id,|behaviour,world| {
+                    if let Some(player) = behaviour.as_player_mut() {
+                        player.set_power(id,world,power);
+                    }
+                });
+
+                format!("power set to {:?}",power)
+
+            },
+            Command::Level(name) => {
+                let answer = format!("loading level '{}'",name);
+                self.next_level = Some(name);
+                answer
+            },
+            Command::TimeScale(scale) => {
+                self.time_scale = scale;
+                self.time_acc = 0.0;
+                format!("time scale set to {}",scale)
+            },
+            Command::UnfreezeAll => {
+
+                let mut count = 0;
+
+                for id in self.world.entities() {
+
+                    let enemy = match self.world.get_behaviour_mut(id) {
+                        Some(behaviour) => {
+                            behaviour.unfreeze();
+                            behaviour.is_enemy()
+                        },
+                        None => false
+                    };
+
+                    // the enemies far away start moving too
+                    if enemy {
+                        self.world.active[id] = true;
+                        count += 1;
+                    }
+
+                }
+
+                format!("{} enemies unfrozen",count)
+
+            },
+            Command::Clear => {
+                self.console.clear();
+                return;
+            },
+            Command::Help => Command::get_help().to_string()
+        };
+
+        self.console.print(&answer);
+
+    }
+
+    fn get_player(&self) -> Option<&Player> {
+        self.world.get_behaviour(self.player).and_then(|behaviour| behaviour.as_player())
+    }
+
+    fn get_player_mut(&mut self) -> Option<&mut Player> {
+        self.world.get_behaviour_mut(self.player).and_then(|behaviour| behaviour.as_player_mut())
+    }
+
+    /// how far the camera moved to the right since the start of the level
+    fn get_scroll(&self) -> f32 { CAMERA_OFFSET.0 - self.camera.offset.x }
+
+    /// with the fire power the player throw a fireball where he look, if one of them isn't already on screen
+    fn throw_fireball(&mut self) {
+
+        let player = self.player;
+
+        let can_throw = self.get_player().is_some_and(|player| {
+            player.get_power() == Power::Fire && !player.is_dying() && !player.has_noclip()
+        });
+
+        let rect = match self.world.get_rect(player) {
+            Some(rect) if can_throw => rect,
+            _ => return
+        };
+
+        let left = self.world.sprites[player].as_ref().is_some_and(|sprite| sprite.is_flipped());
+        let direction = if left { Direction::Left } else { Direction::Right };
+
+        let free = self.world.entities().find(|id| {
+            !self.world.is_active(*id) && matches!(self.world.get_behaviour(*id),Some(Behaviour::Fireball(_)))
+        });
+
+        if let Some(id) = free {
+            self.world.with_behaviour(id,|behaviour,world| {
+                if let Behaviour::Fireball(fireball) = behaviour {
+                    fireball.throw(id,world,&rect,direction);
+                }
+            });
+        }
+
+    }
+
+    fn flip_player(&mut self,flip:bool) {
+        if let Some(sprite) = self.world.get_sprite_mut(self.player) {
+            sprite.flip(flip);
+        }
+    }
+
+    fn is_player_dying(&self) -> bool {
+        self.world.get_behaviour(self.player)
+            .and_then(|behaviour| behaviour.as_player())
+            .is_some_and(|player| player.is_dying())
+    }
+
+    fn player_has_star(&self) -> bool {
+        self.world.get_behaviour(self.player)
+            .and_then(|behaviour| behaviour.as_player())
+            .is_some_and(|player| player.has_star())
+    }
+
+    /// restart the level right away, without losing a life
+    fn reset(&mut self) {
+
+        self.checkpoint = None;
+        self.reset_level();
+
+        self.change_status(GameStatus::GamePlay);
+        self.audio.start_music();
+
+    }
+
+    /// put the level back as it was when it started, or when the player passed the last checkpoint
+    fn reset_level(&mut self) {
+
+        let start_x = match self.checkpoint {
+            Some(checkpoint) => {
+                system::checkpoint_reset_system(&mut self.world,&checkpoint,&self.level.restore);
+                checkpoint.x
+            },
+            None => {
+                system::reset_system(&mut self.world);
+                self.level.player.x
             }
+        };
+
+        self.timer.reset();
+        self.move_camera_to(start_x);
+
+    }
+
+    /// put the camera where it would be if the player walked to x from the start of the level
+    fn move_camera_to(&mut self,x:f32) {
+
+        let scrolled = (x + TILE_SIZE).clamp(200.0,3164.0) - 200.0;
+
+        self.camera.offset = vec2(CAMERA_OFFSET.0 - scrolled/(WIDTH/2.0)*2.4,CAMERA_OFFSET.1);
+        set_camera(&self.camera);
+
+    }
+
+    fn change_status(&mut self,status:GameStatus) {
+        self.game_status = status;
+        self.status_ctn = 0;
+    }
+
+    /// the death animation is over, take a life and try again if there is one left
+    fn lose_life(&mut self) {
 
-            for mbox in self.mystery_blocks.iter_mut() {
-                mbox.update();
+        self.lives = self.lives.saturating_sub(1);
+
+        if self.lives == 0 {
+            self.change_status(GameStatus::GameOver);
+        } else {
+            self.reset_level();
+            self.change_status(GameStatus::Interstitial);
+        }
+
+    }
+
+    /// move the game to the next step of the death, interstitial and game over screens
+    fn update_screens(&mut self,physics:&PhysicsProfile) {
+
+        self.status_ctn += 1;
+
+        match self.game_status {
+
+            GameStatus::Dying => {
+
+                // only the player keep moving during its death animation
+                let player = self.player;
+                self.world.with_behaviour(player,|behaviour,world| behaviour.update(player,world,physics));
+
+                if self.status_ctn >= DEATH_DURATION {
+                    self.lose_life();
+                }
+
+            },
+            GameStatus::Interstitial => {
+
+                if self.status_ctn >= INTERSTITIAL_DURATION {
+                    self.change_status(GameStatus::GamePlay);
+                    self.audio.start_music();
+                }
+
+            },
+            GameStatus::GameOver => {
+
+                // start a new game from the beginning of the level
+                if self.status_ctn >= GAME_OVER_DURATION {
+                    self.lives = START_LIVES;
+                    self.score = 0;
+                    self.coins = 0;
+                    self.checkpoint = None;
+                    self.reset_level();
+                    self.change_status(GameStatus::Interstitial);
+                }
+
+            },
+            GameStatus::GamePlay | GameStatus::Win => {}
+
+        }
+
+    }
+
+    /// advance the world by as many tick as the time scale ask, or by the steps asked while paused
+    pub fn update(&mut self) {
+
+        if self.rewinding {
+            self.step_back();
+            return;
+        }
+
+        if self.paused {
+
+            if self.steps > 0 {
+                self.steps -= 1;
+                self.tick();
             }
 
+            return;
+
+        }
+
+        self.time_acc += self.time_scale;
+
+        while self.time_acc >= 1.0 {
+            self.time_acc -= 1.0;
+            self.tick();
+        }
+
+    }
+
+    /// go to the next slow motion speed, back to the normal one after the slowest
+    fn cycle_time_scale(&mut self) {
+
+        self.time_scale = SLOW_MOTION_SCALES.iter()
+            .position(|scale| *scale == self.time_scale)
+            .and_then(|pos| SLOW_MOTION_SCALES.get(pos + 1))
+            .copied()
+            .unwrap_or(SLOW_MOTION_SCALES[0]);
+
+        self.time_acc = 0.0;
+
+    }
+
+    /// one step of the simulation
+    fn tick(&mut self) {
+
+        // a replay give one input by tick, whatever the speed of the game
+        let input = self.input.next(self.keyboard);
+        self.keyboard.consume();
+
+        if let Some((_,replay)) = &mut self.recording {
+            replay.frames.push(input);
+        }
+
+        self.frame += 1;
+
+        self.physics.poll();
+        let physics = *self.physics.get_profile();
+
+        // the world is frozen while the level is edited
+        if self.editor.is_active() {
+            return;
+        }
+
+        self.apply_input(input);
+
+        if self.game_status == GameStatus::GamePlay {
 
-            //  check if player arrive to a zone and if so activate some goombas
+            self.timer.tick();
 
-            if self.player.get_x() >= 1080.0 {
-                self.goombas[4].unfreeze();
-                self.goombas[5].unfreeze();
+            // the star music keep playing until the star end
+            let had_star = self.player_has_star();
+
+            if self.timer.is_hurry() && !had_star {
+                self.audio.hurry_up();
             }
 
-            if self.player.get_x() >= 1328.0 {
-                self.goombas[6].unfreeze();
-                self.goombas[7].unfreeze();
+            // the player can't finish the level anymore
+            if self.timer.is_over() {
+                let player = self.player;
+                self.world.with_behaviour(player,|behaviour,world| behaviour.die(player,world,&physics));
             }
 
-            if self.player.get_x() >= 1800.0 {
-                self.goombas[8].unfreeze();
-                self.goombas[9].unfreeze();
-                self.goombas[10].unfreeze();
-                self.goombas[11].unfreeze();
+            system::activation_system(&mut self.world,get_view(&self.camera));
+            system::behaviour_system(&mut self.world,&physics);
+            system::movement_system(&mut self.world,self.map.get_solids());
+            system::reaction_system(&mut self.world,&physics);
+            system::animation_system(&mut self.world);
+
+            let events = collect_contact_events(&self.world);
+            self.handle_contact_events(&events,&physics);
+
+            if had_star && !self.player_has_star() {
+                self.resume_music();
             }
 
-            // when player pass the flag he win
-            if self.player.get_x() >= 3200.0 {
-                self.game_status = GameStatus::Win;
+
+            let rect = self.world.get_rect(self.player);
+            // the platforms carry the player without changing his velocity
+            let xvelocity = rect.map_or(0.0,|rect| rect.pos.x - self.world.get_previous(self.player).x);
+
+            if let Some(rect) = rect {
+
+                if rect.get_max_x() >= 200.0
+                    && xvelocity != 0.0
+                    && !self.is_player_dying()
+                    && rect.get_max_x() <= 3164.0 {
+
+
+                    self.camera.offset += vec2(-xvelocity/(WIDTH/2.0)*2.4,0.0);
+                    set_camera(&self.camera);
+
+
+                }
+
+                // the last checkpoint passed is the one the player come back to, walking back doesn't lose it
+                if !self.is_player_dying() {
+                    if let Some(checkpoint) = self.level.checkpoints.iter().rev().find(|c| rect.pos.x >= c.x) {
+                        if self.checkpoint.is_none_or(|current| checkpoint.x > current.x) {
+                            self.checkpoint = Some(*checkpoint);
+                        }
+                    }
+                }
+
+                // when player pass the flag he win
+                if rect.pos.x >= self.level.goal {
+                    self.change_status(GameStatus::Win);
+
+                    self.audio.stop_music();
+                    self.audio.play(SoundEffect::LevelClear);
+
+                    self.clear_level();
+                }
+
+            }
+
+            // whatever killed the player, the world freeze until the death animation end
+            if self.game_status == GameStatus::GamePlay && self.is_player_dying() {
+                self.change_status(GameStatus::Dying);
             }
+
+        } else {
+            self.update_screens(&physics);
         }
 
+        self.play_sounds();
+
+        if self.recording.is_none() && !self.input.is_replay() {
+            let snapshot = self.take_snapshot();
+            self.rewind.push(snapshot);
+        }
 
     }
 
-    pub fn render(&mut self) {
-        match self.game_status {
+    /// count a coin, every hundred coins give a life
+    fn add_coin(&mut self) {
+
+        self.coins += 1;
+        self.score += COIN_SCORE;
+
+        if self.coins >= LIFE_COINS {
+            self.coins -= LIFE_COINS;
+            self.lives += 1;
+            self.world.play_sound(SoundEffect::OneUp);
+        }
+
+    }
+
+    /// play the sounds the entities asked for during the frame
+    fn play_sounds(&mut self) {
 
-            GameStatus::GamePlay => {
-                self.map.render();
-                self.player.draw();
+        for effect in self.world.take_sounds() {
 
-                for goomba in self.goombas.iter() {
-                    if !goomba.is_disappear() {
-                        goomba.draw();
+            // nothing else should be heard over the death jingle
+            if effect == SoundEffect::Death {
+                self.audio.stop_music();
+            }
+
+            self.audio.play(effect);
+
+        }
+
+    }
+
+    /// gameplay reaction to what happened during the collision pass
+    fn handle_contact_events(&mut self,events:&[ContactEvent],physics:&PhysicsProfile) {
+
+        let player = self.player;
+
+        for event in events.iter() {
+
+            match *event {
+
+                // with the star every enemy the player touch is knocked off
+                ContactEvent::PlayerStompedEnemy(id) | ContactEvent::EnemyHitPlayer(id) if self.player_has_star() => {
+                    self.world.with_behaviour(id,|behaviour,world| behaviour.knock_off(id,world,physics));
+                    self.score += STOMP_SCORE;
+                },
+                ContactEvent::PlayerStompedEnemy(id) => {
+                    self.world.with_behaviour(id,|behaviour,world| behaviour.die(id,world,physics));
+                    self.score += STOMP_SCORE;
+                },
+                ContactEvent::EnemyHitPlayer(_) => {
+                    self.world.with_behaviour(player,|behaviour,world| {
+                        if let Some(p) = behaviour.as_player_mut() {
+                            p.hurt(player,world,physics);
+                        }
+                    });
+                },
+                ContactEvent::PlayerHitBlockFromBelow(id) => {
+
+                    let mut coin = false;
+                    self.world.with_behaviour(id,|behaviour,world| coin = behaviour.bump(id,world));
+
+                    if coin {
+                        self.add_coin();
                     }
 
-                }
+                },
+                ContactEvent::PlayerCollected(id) => {
 
-                for mbox in self.mystery_blocks.iter() {
-                    mbox.draw()
-                }
+                    let mut pickup = None;
+                    self.world.with_behaviour(id,|behaviour,world| pickup = behaviour.touch(id,world));
 
+                    match pickup {
+                        Some(Pickup::Coin) => self.add_coin(),
+                        Some(Pickup::Star) => {
 
-            },
-            GameStatus::Win => {
-                self.map.render();
-                self.player.draw();
+                            self.world.with_behaviour(player,|behaviour,_| {
+                                if let Some(p) = behaviour.as_player_mut() {
+                                    p.give_star();
+                                }
+                            });
+
+                            self.score += POWER_UP_SCORE;
+                            self.audio.star_power();
+
+                        },
+                        Some(Pickup::FireFlower) => {
+
+                            self.world.with_behaviour(player,|behaviour,world| {
+                                if let Some(p) = behaviour.as_player_mut() {
+                                    p.set_power(player,world,Power::Fire);
+                                }
+                            });
+
+                            self.score += POWER_UP_SCORE;
+                            self.world.play_sound(SoundEffect::PowerUp);
 
+                        },
+                        None => {}
+                    }
+
+                },
+                ContactEvent::FireballHitEnemy(fireball,id) => {
 
-                for goomba in self.goombas.iter() {
-                    if !goomba.is_disappear() {
-                        goomba.draw();
+                    self.world.with_behaviour(fireball,|behaviour,world| behaviour.explode(fireball,world));
+
+                    // two fireballs can hit the same enemy on one frame
+                    if self.world.get_behaviour(id).is_some_and(|behaviour| behaviour.is_enemy()) {
+                        self.world.with_behaviour(id,|behaviour,world| behaviour.knock_off(id,world,physics));
+                        self.score += STOMP_SCORE;
                     }
 
+                },
+                // walk back in the direction the wall face
+                ContactEvent::EnemyHitWall(id,side) => {
+                    if let Some(behaviour) = self.world.get_behaviour_mut(id) {
+                        behaviour.turn_around(side);
+                    }
+                },
+                ContactEvent::FellOutOfWorld(id) if id == player => {
+                    self.world.with_behaviour(player,|behaviour,world| {
+                        if let Some(player) = behaviour.as_player_mut() {
+                            player.fall(id,world);
+                        }
+                    });
+                },
+                ContactEvent::FellOutOfWorld(id) => {
+                    self.world.with_behaviour(id,|behaviour,world| behaviour.despawn(id,world));
                 }
 
-                for mbox in self.mystery_blocks.iter() {
-                    mbox.draw()
+            }
+
+        }
+
+    }
+
+    pub fn render(&mut self) {
+
+        self.render_game();
+
+        // drawn on top of the level, the editor and the screens alike
+        self.debug.render(&self.camera,&self.world,&self.map,self.frame);
+        self.render_time_info();
+        self.console.render();
+
+        set_camera(&self.camera);
+
+    }
+
+    fn render_game(&mut self) {
+
+        clear_background(self.map.get_background());
+
+        if self.editor.is_active() {
+            self.map.render_all(&self.camera,self.get_scroll());
+            self.editor.render(&self.camera,&self.map,&self.level);
+            return;
+        }
+
+        match self.game_status {
+
+            GameStatus::Interstitial => {
+                let level = format!("WORLD {}",self.map.get_name().trim_start_matches("lvl-"));
+                let lives = format!("LIVES  x  {}",self.lives);
+                let score = format!("SCORE {}   TOP {}",self.score,self.save.get_high_score(self.map.get_name()));
+
+                self.render_screen(&[&level,&lives,&score]);
+                return;
+            },
+            GameStatus::GameOver => {
+                self.render_screen(&["GAME OVER"]);
+                return;
+            },
+            _ => {}
+
+        }
+
+        let scroll = self.get_scroll();
+
+        self.map.render(Depth::Background,&self.camera,scroll);
+        self.map.render(Depth::Midground,&self.camera,scroll);
+
+        system::render_system(&self.world,get_view(&self.camera));
+
+        self.map.render(Depth::Foreground,&self.camera,scroll);
+        self.map.render(Depth::Overlay,&self.camera,scroll);
+
+        self.render_hud();
+
+        if self.game_status == GameStatus::Win {
+
+            draw_texture_ex(
+                self.win_message,
+                (2968.0 / (WIDTH/2.0)) - 1.0,
+                (90.0 / (HEIGHT/2.0)) - 1.0,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(vec2(
+                        400.0 / (WIDTH/2.0),
+                        200.0 / (HEIGHT/2.0)
+                    )),
+                    source: None,
+                    pivot: None,
+                    flip_y: true,
+                    flip_x: false,
+                    rotation: 0.0
                 }
+            );
 
 
-                draw_texture_ex(
-                    self.win_message,
-                    (2968.0 / (WIDTH/2.0)) - 1.0,
-                    (90.0 / (HEIGHT/2.0)) - 1.0,
-                    WHITE,
-                    DrawTextureParams {
-                        dest_size: Some(vec2(
-                            400.0 / (WIDTH/2.0),
-                            200.0 / (HEIGHT/2.0)
-                        )),
-                        source: None,
-                        pivot: None,
-                        flip_y: true,
-                        flip_x: false,
-                        rotation: 0.0
-                    }
-                );
+        }
 
+    }
 
-            }
+
+    /// score, coins, lives and time left on the top of the screen
+    fn render_hud(&self) {
+
+        set_default_camera();
+
+        let hud = format!(
+            "SCORE {:06}   COINS x{:02}   LIVES x{}   TIME {:03}",
+            self.score,
+            self.coins,
+            self.lives,
+            self.timer.get_time()
+        );
+
+        draw_text(&hud,10.0,30.0,28.0,WHITE);
+
+        set_camera(&self.camera);
+
+    }
+
+    /// remind that the game doesn't run at its normal speed
+    fn render_time_info(&self) {
+
+        let mut info = Vec::new();
+
+        if self.rewinding {
+            let seconds = self.rewind.len() as f32 / 60.0;
+            info.push(format!("REWIND  {:.1}s left",seconds));
+        } else if self.paused {
+            info.push("PAUSED  F5 resume  F6 step".to_string());
+        } else if self.time_scale != 1.0 {
+            info.push(format!("SPEED x{}  F7 change",self.time_scale));
+        }
+
+        if self.input.is_replay() {
+            info.push("REPLAY".to_string());
+        }
+
+        if info.is_empty() {
+            return;
+        }
+
+        set_default_camera();
+
+        draw_text(&info.join("  "),10.0,screen_height() - 12.0,24.0,YELLOW);
+
+    }
+
+    /// black screen with some centered lines of text, drawn outside of the level camera
+    fn render_screen(&self,lines:&[&str]) {
+
+        clear_background(BLACK);
+        set_default_camera();
+
+        let font_size = 40.0;
+        let line_height = font_size * 1.5;
+        let top = (screen_height() - line_height * lines.len() as f32) / 2.0;
+
+        for (i,line) in lines.iter().enumerate() {
+
+            let size = measure_text(line,None,font_size as u16,1.0);
+
+            draw_text(
+                line,
+                (screen_width() - size.width) / 2.0,
+                top + line_height * (i as f32 + 1.0),
+                font_size,
+                WHITE
+            );
 
         }
 
+        set_camera(&self.camera);
 
     }
 
 
-}
\ No newline at end of file
+}
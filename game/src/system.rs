@@ -0,0 +1,258 @@
+
+use crate::collision::{Contact, Solid, SolidKind, is_standing_on, move_and_collide};
+use crate::declaration::TILE_SIZE;
+use crate::level::{Checkpoint, RestoreRule, RestoreRules};
+use crate::physics::PhysicsProfile;
+use crate::world::{Behaviour, World};
+
+use lib_game::shape::Rect;
+use lib_game::vector::Vec2;
+
+
+/// number of pixel outside of the view where an enemy start to move
+const SPAWN_DISTANCE: f32 = 32.0;
+/// number of pixel an enemy can be behind the view before it is removed
+const DESPAWN_DISTANCE: f32 = 48.0;
+
+
+/// start the entities the camera reached and remove the ones it left far behind
+pub fn activation_system(world:&mut World,view:(f32,f32)) {
+
+    let (left,right) = view;
+
+    for id in world.entities() {
+
+        let spawn_on_approach = world.get_behaviour(id).is_some_and(|behaviour| behaviour.spawn_on_approach());
+        let gone = world.get_behaviour(id).is_some_and(|behaviour| behaviour.is_gone());
+        let despawn_off_view = world.get_behaviour(id).is_some_and(|behaviour| behaviour.despawn_off_view());
+
+        if despawn_off_view && world.active[id] {
+
+            if world.get_rect(id).is_some_and(|rect| rect.get_max_x() < left || rect.pos.x > right) {
+                world.with_behaviour(id,|behaviour,world| behaviour.despawn(id,world));
+            }
+
+            continue;
+
+        }
+
+        let rect = match world.get_rect(id) {
+            Some(rect) if spawn_on_approach => rect,
+            _ => continue
+        };
+
+        if !world.active[id] {
+
+            // an entity despawned behind the view stay inactive until the level is reset
+            if !gone && rect.pos.x <= right + SPAWN_DISTANCE {
+                world.active[id] = true;
+            }
+
+        } else if rect.get_max_x() < left - DESPAWN_DISTANCE {
+
+            world.with_behaviour(id,|behaviour,world| behaviour.despawn(id,world));
+            world.active[id] = false;
+
+        }
+
+    }
+
+}
+
+/// let every entity decide its velocity and its sprite before they move
+pub fn behaviour_system(world:&mut World,physics:&PhysicsProfile) {
+
+    for id in world.active_entities() {
+        world.with_behaviour(id,|behaviour,world| behaviour.update(id,world,physics));
+    }
+
+}
+
+/// move every entity that has a velocity, the tiles and the solid entities stop them
+pub fn movement_system(world:&mut World,tiles:&[Solid]) {
+
+    let World { positions, velocities, colliders, contacts, previous, active, .. } = world;
+
+    // the moving solids, like the platforms, go first and go through everything
+    let mut carriers = vec![None; positions.len()];
+
+    for id in 0..positions.len() {
+
+        if !active[id] || !colliders[id].is_some_and(|collider| collider.solid) {
+            continue;
+        }
+
+        if let (Some(position),Some(velocity)) = (positions[id].as_mut(),velocities[id]) {
+
+            previous[id] = *position;
+            position.x += velocity.x;
+            position.y += velocity.y;
+
+            if velocity.x != 0.0 || velocity.y != 0.0 {
+                carriers[id] = Some(velocity);
+            }
+
+        }
+
+    }
+
+    // the solid entities are collected at their new place once for every one
+    let entity_solids: Vec<Solid> = (0..positions.len())
+        .filter_map(|id| match (positions[id],colliders[id]) {
+            (Some(pos),Some(collider)) if collider.solid => Some(Solid {
+                from_below: collider.from_below,
+                ..Solid::new(Rect::new(pos.x,pos.y,collider.width,collider.height),SolidKind::Entity(id))
+            }),
+            _ => None
+        })
+        .collect();
+
+    for id in 0..positions.len() {
+
+        // a body that stood on a moving solid at the end of the last movement is carried by it
+        let carry = contacts[id].iter()
+            .filter(|contact| contact.is_ground())
+            .find_map(|contact| match contact.kind {
+                SolidKind::Entity(solid) => carriers[solid],
+                SolidKind::Tile => None
+            });
+
+        contacts[id].clear();
+
+        if !active[id] {
+            continue;
+        }
+
+        let (position,velocity,collider) = match (positions[id].as_mut(),velocities[id].as_mut(),colliders[id]) {
+            (Some(position),Some(velocity),Some(collider)) if !collider.solid => (position,velocity,collider),
+            _ => continue
+        };
+
+        previous[id] = *position;
+
+        if collider.ghost {
+            position.x += velocity.x;
+            position.y += velocity.y;
+            continue;
+        }
+
+        let mut shape = Rect::new(position.x,position.y,collider.width,collider.height);
+        let solids = tiles.iter().chain(entity_solids.iter());
+
+        // the ground move the body first, a wall can still stop it
+        if let Some(mut carry) = carry {
+            contacts[id] = move_and_collide(&mut shape,&mut carry,solids.clone());
+        }
+
+        let moved = move_and_collide(&mut shape,velocity,solids);
+        contacts[id].extend(moved);
+
+        // a body that doesn't go up keep standing on a moving solid, even when it rise under it
+        if velocity.y <= 0.0 && !contacts[id].iter().any(|contact| contact.is_ground()) {
+
+            let ground = entity_solids.iter()
+                .find(|solid| matches!(solid.kind,SolidKind::Entity(other) if carriers[other].is_some()) && is_standing_on(&shape,&solid.rect));
+
+            if let Some(ground) = ground {
+                contacts[id].push(Contact { kind: ground.kind, normal: Vec2::new(0.0,1.0) });
+            }
+
+        }
+
+        *position = shape.pos;
+
+    }
+
+}
+
+/// let every entity react to what it touched while moving
+pub fn reaction_system(world:&mut World,physics:&PhysicsProfile) {
+
+    for id in world.active_entities() {
+        world.with_behaviour(id,|behaviour,world| behaviour.after_move(id,world,physics));
+    }
+
+}
+
+/// advance every sprite animation and tell the behaviour when a one-shot clip end
+pub fn animation_system(world:&mut World) {
+
+    for id in world.active_entities() {
+
+        let finished = match world.get_sprite_mut(id) {
+            Some(sprite) => {
+                if !sprite.tick() {
+                    continue;
+                }
+                sprite.get_clip().to_string()
+            },
+            None => continue
+        };
+
+        world.with_behaviour(id,|behaviour,world| behaviour.animation_finished(id,world,&finished));
+
+    }
+
+}
+
+/// put back every entity as it was when the level started
+pub fn reset_system(world:&mut World) {
+
+    for id in world.entities() {
+        world.with_behaviour(id,|behaviour,world| behaviour.reset(id,world));
+    }
+
+}
+
+/// bring the player back to the checkpoint, the other entities follow the restore rules
+pub fn checkpoint_reset_system(world:&mut World,checkpoint:&Checkpoint,rules:&RestoreRules) {
+
+    for id in world.entities() {
+        world.with_behaviour(id,|behaviour,world| {
+
+            let rule = match behaviour {
+                Behaviour::Player(player) => {
+                    player.respawn(id,world,Vec2::new(checkpoint.x,checkpoint.y));
+                    return;
+                },
+                Behaviour::Goomba(_) => rules.enemies,
+                // the coins and the items follow the same rule as the blocks that give them
+                Behaviour::MysteryBlock(_) | Behaviour::Coin(_) | Behaviour::Star(_) | Behaviour::FireFlower(_) => rules.blocks,
+                // a platform is always where its path say it is at the start, and no fireball is left
+                Behaviour::Platform(_) | Behaviour::Fireball(_) => RestoreRule::Reset
+            };
+
+            let reset = match rule {
+                RestoreRule::Reset => true,
+                RestoreRule::Keep => false,
+                RestoreRule::Ahead => behaviour.get_start().x >= checkpoint.x
+            };
+
+            if reset {
+                behaviour.reset(id,world);
+            }
+
+        });
+    }
+
+}
+
+/// draw every visible sprite inside the view at the position of its entity
+pub fn render_system(world:&World,view:(f32,f32)) {
+
+    let (left,right) = view;
+
+    for id in world.active_entities() {
+
+        // the wide entities, like the platforms, are drawn as long as a part of them is seen
+        let width = world.colliders[id].map_or(TILE_SIZE,|collider| collider.width.max(TILE_SIZE));
+
+        if let (Some(position),Some(sprite)) = (world.positions[id].as_ref(),world.sprites[id].as_ref()) {
+            if sprite.visible && position.x + width >= left && position.x <= right {
+                sprite.draw(position);
+            }
+        }
+
+    }
+
+}
@@ -0,0 +1,763 @@
+
+use lib_game::GResult;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+
+/// the highest bits of a gid tell if the tile is flipped, they are ignored
+const FLIP_FLAGS: u32 = 0xF000_0000;
+
+
+/// value of a custom property set in Tiled
+#[derive(Debug,Clone,PartialEq)]
+pub enum Property {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String)
+}
+
+#[derive(Debug,Clone,Default)]
+pub struct Properties(HashMap<String,Property>);
+
+impl Properties {
+
+    pub fn get_bool(&self,name:&str) -> Option<bool> {
+        match self.0.get(name) {
+            Some(Property::Bool(value)) => Some(*value),
+            _ => None
+        }
+    }
+
+    pub fn get_f32(&self,name:&str) -> Option<f32> {
+        match self.0.get(name) {
+            Some(Property::Float(value)) => Some(*value as f32),
+            Some(Property::Int(value)) => Some(*value as f32),
+            _ => None
+        }
+    }
+
+    pub fn get_str(&self,name:&str) -> Option<&str> {
+        match self.0.get(name) {
+            Some(Property::String(value)) => Some(value),
+            _ => None
+        }
+    }
+
+    fn insert(&mut self,name:&str,kind:&str,value:Property) -> GResult<()> {
+
+        let value = match (kind,value) {
+            // Tiled write every value as a string in the tmx file
+            ("bool",Property::String(value)) => Property::Bool(value == "true"),
+            ("int",Property::String(value)) => Property::Int(parse(name,&value)?),
+            ("float",Property::String(value)) => Property::Float(parse(name,&value)?),
+            ("float",Property::Int(value)) => Property::Float(value as f64),
+            (_,value) => value
+        };
+
+        self.0.insert(name.to_string(),value);
+
+        Ok(())
+
+    }
+
+}
+
+
+/// one tile of a tileset, the image is shared with the other tiles of an atlas
+#[derive(Debug,Clone)]
+pub struct TiledTile {
+    pub gid:        u32,
+    /// path of the image, relative to the working directory
+    pub image:      String,
+    /// part of the image to draw as x, y, width and height, the whole image when none
+    pub source:     Option<(f32,f32,f32,f32)>,
+    pub properties: Properties
+}
+
+#[derive(Debug,Clone)]
+pub struct TileLayer {
+    pub name:       String,
+    /// gid of each cell row by row from the top, 0 is an empty cell
+    pub gids:       Vec<u32>,
+    /// horizontal scroll factor of the layer
+    pub parallax:   f32,
+    pub properties: Properties
+}
+
+#[derive(Debug,Clone)]
+pub struct TiledObject {
+    pub name:       String,
+    /// class of the object, tell which entity or trigger it is
+    pub kind:       String,
+    pub x:          f32,
+    pub y:          f32,
+    pub width:      f32,
+    pub height:     f32,
+    /// tile objects are placed by their bottom left corner instead of their top left one
+    pub gid:        Option<u32>,
+    pub properties: Properties
+}
+
+impl TiledObject {
+
+    /// y of the bottom of the object, counted from the top of the map
+    pub fn get_bottom(&self) -> f32 {
+        if self.gid.is_some() { self.y } else { self.y + self.height }
+    }
+
+}
+
+/// everything the game use from a map made with the Tiled editor
+#[derive(Debug,Clone)]
+pub struct TiledMap {
+    pub width:          usize,
+    pub height:         usize,
+    pub tile_width:     f32,
+    pub tile_height:    f32,
+    /// color as #rrggbb or #aarrggbb
+    pub background:     Option<String>,
+    pub tiles:          Vec<TiledTile>,
+    /// in drawing order, the groups are flattened
+    pub tile_layers:    Vec<TileLayer>,
+    /// objects of every object layer
+    pub objects:        Vec<TiledObject>,
+    pub properties:     Properties
+}
+
+impl TiledMap {
+
+    /// load a .tmx or .tmj map with its external tilesets
+    pub fn load(path:&str) -> GResult<Self> {
+
+        let map = match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("tmx") => xml::load_map(path),
+            Some("tmj") | Some("json") => json::load_map(path),
+            _ => bail!("'{}' is not a tiled map",path)
+        };
+
+        match map {
+            Ok(map) => Ok(map),
+            Err(e) => bail!("can't load tiled map '{}': {}",path,e)
+        }
+
+    }
+
+}
+
+
+/// tileset as written in the file, before its tiles get their gid
+#[derive(Default)]
+struct TilesetData {
+    /// image of an atlas tileset
+    image:          Option<String>,
+    columns:        u32,
+    tile_count:     u32,
+    tile_width:     f32,
+    tile_height:    f32,
+    margin:         f32,
+    spacing:        f32,
+    /// tiles with their own image or with properties
+    tiles:          Vec<(u32,Option<String>,Properties)>
+}
+
+impl TilesetData {
+
+    /// give every tile its gid and the path of its image
+    fn expand(self,first_gid:u32,dir:&Path,tiles:&mut Vec<TiledTile>) {
+
+        let mut properties: HashMap<u32,Properties> = HashMap::new();
+        let mut images: Vec<(u32,String)> = Vec::new();
+
+        for (id,image,props) in self.tiles.into_iter() {
+            if let Some(image) = image {
+                images.push((id,image));
+            }
+            properties.insert(id,props);
+        }
+
+        match self.image {
+
+            Some(image) => {
+
+                let image = join(dir,&image);
+                let columns = self.columns.max(1);
+
+                for id in 0..self.tile_count {
+
+                    let x = self.margin + (id % columns) as f32 * (self.tile_width + self.spacing);
+                    let y = self.margin + (id / columns) as f32 * (self.tile_height + self.spacing);
+
+                    tiles.push(TiledTile {
+                        gid: first_gid + id,
+                        image: image.clone(),
+                        source: Some((x,y,self.tile_width,self.tile_height)),
+                        properties: properties.remove(&id).unwrap_or_default()
+                    });
+
+                }
+
+            },
+            None => {
+
+                for (id,image) in images.into_iter() {
+                    tiles.push(TiledTile {
+                        gid: first_gid + id,
+                        image: join(dir,&image),
+                        source: None,
+                        properties: properties.remove(&id).unwrap_or_default()
+                    });
+                }
+
+            }
+
+        }
+
+    }
+
+}
+
+
+fn parse<T:FromStr>(name:&str,value:&str) -> GResult<T> {
+    match value.trim().parse() {
+        Ok(value) => Ok(value),
+        Err(_) => bail!("invalid value '{}' for '{}'",value,name)
+    }
+}
+
+fn join(dir:&Path,file:&str) -> String {
+    let path: PathBuf = dir.join(file);
+    path.to_string_lossy().into_owned()
+}
+
+fn get_dir(path:&str) -> PathBuf {
+    Path::new(path).parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+fn remove_flags(gid:u32) -> u32 { gid & !FLIP_FLAGS }
+
+/// an external tileset can be a .tsx or a .tsj file whatever the format of the map
+fn load_tileset_file(path:&str) -> GResult<TilesetData> {
+    match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("tsx") => xml::load_tileset_file(path),
+        _ => json::load_tileset_file(path)
+    }
+}
+
+/// a map can only be loaded if its layers cover the whole map
+fn check_layer(name:&str,gids:&[u32],width:usize,height:usize) -> GResult<()> {
+    if gids.len() != width * height {
+        bail!("layer '{}' has {} cells instead of {}",name,gids.len(),width * height);
+    }
+    Ok(())
+}
+
+
+
+/// .tmx map and .tsx tileset
+mod xml {
+
+    use super::*;
+
+    use roxmltree::{Document, Node};
+
+
+    fn attr<T:FromStr>(node:Node,name:&str) -> GResult<T> {
+        match node.attribute(name) {
+            Some(value) => parse(name,value),
+            None => bail!("<{}> has no '{}' attribute",node.tag_name().name(),name)
+        }
+    }
+
+    fn attr_or<T:FromStr>(node:Node,name:&str,default:T) -> GResult<T> {
+        match node.attribute(name) {
+            Some(value) => parse(name,value),
+            None => Ok(default)
+        }
+    }
+
+    fn children<'a,'input>(node:Node<'a,'input>,tag:&'static str) -> impl Iterator<Item = Node<'a,'input>> {
+        node.children().filter(move |child| child.has_tag_name(tag))
+    }
+
+    fn load_properties(node:Node) -> GResult<Properties> {
+
+        let mut properties = Properties::default();
+
+        for list in children(node,"properties") {
+            for property in children(list,"property") {
+
+                let name: String = attr(property,"name")?;
+                let kind = property.attribute("type").unwrap_or("string");
+
+                // long string are written as the text of the node
+                let value = property.attribute("value")
+                    .or_else(|| property.text())
+                    .unwrap_or("");
+
+                properties.insert(&name,kind,Property::String(value.to_string()))?;
+
+            }
+        }
+
+        Ok(properties)
+
+    }
+
+    fn load_tileset(node:Node) -> GResult<TilesetData> {
+
+        let mut tileset = TilesetData {
+            columns: attr_or(node,"columns",0)?,
+            tile_count: attr_or(node,"tilecount",0)?,
+            tile_width: attr_or(node,"tilewidth",0.0)?,
+            tile_height: attr_or(node,"tileheight",0.0)?,
+            margin: attr_or(node,"margin",0.0)?,
+            spacing: attr_or(node,"spacing",0.0)?,
+            ..Default::default()
+        };
+
+        if let Some(image) = children(node,"image").next() {
+            tileset.image = Some(attr(image,"source")?);
+        }
+
+        for tile in children(node,"tile") {
+
+            let image = match children(tile,"image").next() {
+                Some(image) => Some(attr(image,"source")?),
+                None => None
+            };
+
+            tileset.tiles.push((attr(tile,"id")?,image,load_properties(tile)?));
+
+        }
+
+        Ok(tileset)
+
+    }
+
+    fn load_data(node:Node,name:&str) -> GResult<Vec<u32>> {
+
+        let data = match children(node,"data").next() {
+            Some(data) => data,
+            None => bail!("layer '{}' has no data",name)
+        };
+
+        match data.attribute("encoding") {
+            Some("csv") => {
+                data.text().unwrap_or("")
+                    .split(',')
+                    .filter(|gid| !gid.trim().is_empty())
+                    .map(|gid| parse(name,gid).map(remove_flags))
+                    .collect()
+            },
+            None => {
+                children(data,"tile")
+                    .map(|tile| attr_or(tile,"gid",0).map(remove_flags))
+                    .collect()
+            },
+            Some(encoding) => bail!("layer '{}' use the '{}' encoding, save the map with the csv layer format",name,encoding)
+        }
+
+    }
+
+    fn load_object(node:Node) -> GResult<TiledObject> {
+
+        // the class was called type before Tiled 1.9
+        let kind = node.attribute("type")
+            .or_else(|| node.attribute("class"))
+            .unwrap_or("")
+            .to_string();
+
+        Ok(TiledObject {
+            name: node.attribute("name").unwrap_or("").to_string(),
+            kind,
+            x: attr(node,"x")?,
+            y: attr(node,"y")?,
+            width: attr_or(node,"width",0.0)?,
+            height: attr_or(node,"height",0.0)?,
+            gid: match node.attribute("gid") {
+                Some(gid) => Some(remove_flags(parse("gid",gid)?)),
+                None => None
+            },
+            properties: load_properties(node)?
+        })
+
+    }
+
+    fn load_layers(node:Node,map:&mut TiledMap) -> GResult<()> {
+
+        for child in node.children().filter(|child| child.is_element()) {
+
+            match child.tag_name().name() {
+
+                "layer" => {
+
+                    let name: String = attr_or(child,"name",String::new())?;
+                    let gids = load_data(child,&name)?;
+
+                    check_layer(&name,&gids,map.width,map.height)?;
+
+                    map.tile_layers.push(TileLayer {
+                        name,
+                        gids,
+                        parallax: attr_or(child,"parallaxx",1.0)?,
+                        properties: load_properties(child)?
+                    });
+
+                },
+                "objectgroup" => {
+
+                    for object in children(child,"object") {
+                        map.objects.push(load_object(object)?);
+                    }
+
+                },
+                "group" => load_layers(child,map)?,
+                _ => {}
+
+            }
+
+        }
+
+        Ok(())
+
+    }
+
+    pub fn load_tileset_file(path:&str) -> GResult<TilesetData> {
+
+        let content = fs::read_to_string(path)?;
+        let document = Document::parse(&content)?;
+
+        load_tileset(document.root_element())
+
+    }
+
+    pub fn load_map(path:&str) -> GResult<TiledMap> {
+
+        let content = fs::read_to_string(path)?;
+        let document = Document::parse(&content)?;
+        let root = document.root_element();
+
+        if attr_or(root,"infinite",0)? != 0 {
+            bail!("infinite maps are not supported");
+        }
+
+        let dir = get_dir(path);
+
+        let mut map = TiledMap {
+            width: attr(root,"width")?,
+            height: attr(root,"height")?,
+            tile_width: attr(root,"tilewidth")?,
+            tile_height: attr(root,"tileheight")?,
+            background: root.attribute("backgroundcolor").map(str::to_string),
+            tiles: Vec::new(),
+            tile_layers: Vec::new(),
+            objects: Vec::new(),
+            properties: load_properties(root)?
+        };
+
+        for tileset in children(root,"tileset") {
+
+            let first_gid: u32 = attr(tileset,"firstgid")?;
+
+            match tileset.attribute("source") {
+                Some(source) => {
+                    let path = join(&dir,source);
+                    super::load_tileset_file(&path)?.expand(first_gid,&get_dir(&path),&mut map.tiles);
+                },
+                None => load_tileset(tileset)?.expand(first_gid,&dir,&mut map.tiles)
+            }
+
+        }
+
+        load_layers(root,&mut map)?;
+
+        Ok(map)
+
+    }
+
+}
+
+
+
+/// .tmj map and .tsj tileset
+mod json {
+
+    use super::*;
+
+
+    #[derive(Deserialize)]
+    struct JsonProperty {
+        name:       String,
+        #[serde(rename = "type", default)]
+        kind:       String,
+        value:      Value
+    }
+
+    #[derive(Deserialize)]
+    struct JsonTile {
+        id:         u32,
+        #[serde(default)]
+        image:      Option<String>,
+        #[serde(default)]
+        properties: Vec<JsonProperty>
+    }
+
+    #[derive(Deserialize,Default)]
+    #[serde(default)]
+    struct JsonTileset {
+        image:      Option<String>,
+        columns:    u32,
+        tilecount:  u32,
+        tilewidth:  f32,
+        tileheight: f32,
+        margin:     f32,
+        spacing:    f32,
+        tiles:      Vec<JsonTile>
+    }
+
+    #[derive(Deserialize)]
+    struct JsonTilesetRef {
+        firstgid:   u32,
+        #[serde(default)]
+        source:     Option<String>,
+        /// used when the tileset is embedded in the map
+        #[serde(flatten)]
+        tileset:    JsonTileset
+    }
+
+    #[derive(Deserialize)]
+    struct JsonObject {
+        #[serde(default)]
+        name:       String,
+        #[serde(rename = "type", default)]
+        kind:       String,
+        #[serde(default)]
+        class:      String,
+        x:          f32,
+        y:          f32,
+        #[serde(default)]
+        width:      f32,
+        #[serde(default)]
+        height:     f32,
+        #[serde(default)]
+        gid:        Option<u32>,
+        #[serde(default)]
+        properties: Vec<JsonProperty>
+    }
+
+    #[derive(Deserialize)]
+    struct JsonLayer {
+        #[serde(rename = "type")]
+        kind:       String,
+        #[serde(default)]
+        name:       String,
+        #[serde(default)]
+        data:       Option<Value>,
+        #[serde(default)]
+        objects:    Vec<JsonObject>,
+        /// children of a group layer
+        #[serde(default)]
+        layers:     Vec<JsonLayer>,
+        #[serde(default = "default_parallax")]
+        parallaxx:  f32,
+        #[serde(default)]
+        properties: Vec<JsonProperty>
+    }
+
+    #[derive(Deserialize)]
+    struct JsonMap {
+        width:      usize,
+        height:     usize,
+        tilewidth:  f32,
+        tileheight: f32,
+        #[serde(default)]
+        infinite:   bool,
+        #[serde(default)]
+        backgroundcolor: Option<String>,
+        layers:     Vec<JsonLayer>,
+        #[serde(default)]
+        tilesets:   Vec<JsonTilesetRef>,
+        #[serde(default)]
+        properties: Vec<JsonProperty>
+    }
+
+
+    fn default_parallax() -> f32 { 1.0 }
+
+    fn read<T:for<'de> Deserialize<'de>>(path:&str) -> GResult<T> {
+
+        let content = fs::read_to_string(path)?;
+
+        match serde_json::from_str(&content) {
+            Ok(value) => Ok(value),
+            Err(e) => bail!("invalid json in '{}': {}",path,e)
+        }
+
+    }
+
+    fn load_properties(list:Vec<JsonProperty>) -> GResult<Properties> {
+
+        let mut properties = Properties::default();
+
+        for property in list.into_iter() {
+
+            let value = match property.value {
+                Value::Bool(value) => Property::Bool(value),
+                Value::Number(number) => match number.as_i64() {
+                    Some(value) => Property::Int(value),
+                    None => Property::Float(number.as_f64().unwrap_or(0.0))
+                },
+                Value::String(value) => Property::String(value),
+                value => Property::String(value.to_string())
+            };
+
+            properties.insert(&property.name,&property.kind,value)?;
+
+        }
+
+        Ok(properties)
+
+    }
+
+    fn load_tileset(tileset:JsonTileset) -> GResult<TilesetData> {
+
+        let mut tiles = Vec::new();
+
+        for tile in tileset.tiles.into_iter() {
+            tiles.push((tile.id,tile.image,load_properties(tile.properties)?));
+        }
+
+        Ok(TilesetData {
+            image: tileset.image,
+            columns: tileset.columns,
+            tile_count: tileset.tilecount,
+            tile_width: tileset.tilewidth,
+            tile_height: tileset.tileheight,
+            margin: tileset.margin,
+            spacing: tileset.spacing,
+            tiles
+        })
+
+    }
+
+    fn load_data(data:Option<Value>,name:&str) -> GResult<Vec<u32>> {
+
+        match data {
+            Some(Value::Array(gids)) => {
+                gids.iter()
+                    .map(|gid| match gid.as_u64() {
+                        Some(gid) => Ok(remove_flags(gid as u32)),
+                        None => bail!("invalid gid '{}' in layer '{}'",gid,name)
+                    })
+                    .collect()
+            },
+            Some(_) => bail!("layer '{}' is encoded, save the map with the csv layer format",name),
+            None => bail!("layer '{}' has no data",name)
+        }
+
+    }
+
+    fn load_layers(layers:Vec<JsonLayer>,map:&mut TiledMap) -> GResult<()> {
+
+        for layer in layers.into_iter() {
+
+            match layer.kind.as_str() {
+
+                "tilelayer" => {
+
+                    let gids = load_data(layer.data,&layer.name)?;
+
+                    check_layer(&layer.name,&gids,map.width,map.height)?;
+
+                    map.tile_layers.push(TileLayer {
+                        name: layer.name,
+                        gids,
+                        parallax: layer.parallaxx,
+                        properties: load_properties(layer.properties)?
+                    });
+
+                },
+                "objectgroup" => {
+
+                    for object in layer.objects.into_iter() {
+
+                        // the class was called type before Tiled 1.9
+                        let kind = if object.kind.is_empty() { object.class } else { object.kind };
+
+                        map.objects.push(TiledObject {
+                            name: object.name,
+                            kind,
+                            x: object.x,
+                            y: object.y,
+                            width: object.width,
+                            height: object.height,
+                            gid: object.gid.map(remove_flags),
+                            properties: load_properties(object.properties)?
+                        });
+
+                    }
+
+                },
+                "group" => load_layers(layer.layers,map)?,
+                _ => {}
+
+            }
+
+        }
+
+        Ok(())
+
+    }
+
+    pub fn load_tileset_file(path:&str) -> GResult<TilesetData> {
+        load_tileset(read(path)?)
+    }
+
+    pub fn load_map(path:&str) -> GResult<TiledMap> {
+
+        let data: JsonMap = read(path)?;
+
+        if data.infinite {
+            bail!("infinite maps are not supported");
+        }
+
+        let dir = get_dir(path);
+
+        let mut map = TiledMap {
+            width: data.width,
+            height: data.height,
+            tile_width: data.tilewidth,
+            tile_height: data.tileheight,
+            background: data.backgroundcolor,
+            tiles: Vec::new(),
+            tile_layers: Vec::new(),
+            objects: Vec::new(),
+            properties: load_properties(data.properties)?
+        };
+
+        for tileset in data.tilesets.into_iter() {
+
+            match tileset.source {
+                Some(source) => {
+                    let path = join(&dir,&source);
+                    super::load_tileset_file(&path)?.expand(tileset.firstgid,&get_dir(&path),&mut map.tiles);
+                },
+                None => load_tileset(tileset.tileset)?.expand(tileset.firstgid,&dir,&mut map.tiles)
+            }
+
+        }
+
+        load_layers(data.layers,&mut map)?;
+
+        Ok(map)
+
+    }
+
+}
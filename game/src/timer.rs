@@ -0,0 +1,55 @@
+
+use serde::{Deserialize, Serialize};
+
+
+/// time given to finish a level
+const START_TIME: u32 = 400;
+/// under this time the music hurry up
+const HURRY_TIME: u32 = 100;
+/// number of game frame in one unit of level time
+const FRAME_PER_UNIT: u32 = 24;
+
+
+/// count down the time left to finish the level
+#[derive(Debug,Clone,Copy,PartialEq,Deserialize,Serialize)]
+pub struct LevelTimer {
+    time:       u32,
+    frame_ctn:  u32
+}
+
+impl LevelTimer {
+
+    pub fn new() -> Self {
+        Self {
+            time: START_TIME,
+            frame_ctn: 0
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.time = START_TIME;
+        self.frame_ctn = 0;
+    }
+
+    /// advance the timer by one frame
+    pub fn tick(&mut self) {
+
+        if self.time == 0 {
+            return;
+        }
+
+        self.frame_ctn += 1;
+
+        if self.frame_ctn >= FRAME_PER_UNIT {
+            self.frame_ctn = 0;
+            self.time -= 1;
+        }
+
+    }
+
+    pub fn get_time(&self) -> u32 { self.time }
+
+    pub fn is_hurry(&self) -> bool { self.time <= HURRY_TIME }
+    pub fn is_over(&self) -> bool { self.time == 0 }
+
+}
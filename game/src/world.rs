@@ -0,0 +1,383 @@
+
+use crate::audio::SoundEffect;
+use crate::collision::Contact;
+use crate::component::{Collider, Sprite};
+use crate::entity::{BlockState, Coin, FireFlower, FlowerState, Goomba, GoombaState, MysteryBlocks, Star, StarState};
+use crate::fireball::{Fireball, FireballState};
+use crate::physics::PhysicsProfile;
+use crate::platform::{Platform, PlatformState};
+use crate::player::{Player, PlayerState};
+
+use lib_game::GResult;
+use lib_game::shape::Rect;
+use lib_game::vector::Vec2;
+use lib_game::Direction;
+
+use serde::{Deserialize, Serialize};
+
+use std::ops::Range;
+
+
+pub type EntityId = usize;
+
+
+/// what an entity do by itself each frame, and the state it need to do it
+pub enum Behaviour {
+    Player(Player),
+    Goomba(Goomba),
+    MysteryBlock(MysteryBlocks),
+    Platform(Platform),
+    Coin(Coin),
+    Star(Star),
+    FireFlower(FireFlower),
+    Fireball(Fireball)
+}
+
+/// the fields of a behaviour that change during the game
+#[derive(Debug,Clone,PartialEq,Deserialize,Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BehaviourState {
+    Player(PlayerState),
+    Goomba(GoombaState),
+    MysteryBlock(BlockState),
+    Platform(PlatformState),
+    Coin { collected: bool },
+    Star(StarState),
+    FireFlower(FlowerState),
+    Fireball(FireballState)
+}
+
+/// what the player got by touching an entity
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Pickup {
+    Coin,
+    Star,
+    FireFlower
+}
+
+
+impl Behaviour {
+
+    /// decide the velocity and the sprite of the entity before it move
+    pub fn update(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
+        match self {
+            Behaviour::Player(player) => player.update(id,world,physics),
+            Behaviour::Goomba(goomba) => goomba.update(id,world,physics),
+            Behaviour::MysteryBlock(block) => block.update(),
+            Behaviour::Platform(platform) => platform.update(id,world,physics),
+            Behaviour::Star(star) => star.update(id,world,physics),
+            Behaviour::FireFlower(flower) => flower.update(id,world),
+            Behaviour::Fireball(fireball) => fireball.update(id,world,physics),
+            Behaviour::Coin(_) => {}
+        }
+    }
+
+    /// react to the solids the entity touched while moving
+    pub fn after_move(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
+        match self {
+            Behaviour::Player(player) => player.after_move(id,world,physics),
+            Behaviour::Goomba(goomba) => goomba.after_move(id,world),
+            Behaviour::Star(star) => star.after_move(id,world),
+            Behaviour::Fireball(fireball) => fireball.after_move(id,world),
+            Behaviour::MysteryBlock(_) | Behaviour::Platform(_) | Behaviour::Coin(_) | Behaviour::FireFlower(_) => {}
+        }
+    }
+
+    /// put back the entity as it was when the level started
+    pub fn reset(&mut self,id:EntityId,world:&mut World) {
+        match self {
+            Behaviour::Player(player) => player.reset(id,world),
+            Behaviour::Goomba(goomba) => goomba.reset(id,world),
+            Behaviour::MysteryBlock(block) => block.reset(id,world),
+            Behaviour::Platform(platform) => platform.reset(id,world),
+            Behaviour::Coin(coin) => coin.reset(id,world),
+            Behaviour::Star(star) => star.reset(id,world),
+            Behaviour::FireFlower(flower) => flower.reset(id,world),
+            Behaviour::Fireball(fireball) => fireball.reset(id,world)
+        }
+    }
+
+    pub fn get_state(&self) -> BehaviourState {
+        match self {
+            Behaviour::Player(player) => BehaviourState::Player(player.get_state()),
+            Behaviour::Goomba(goomba) => BehaviourState::Goomba(goomba.get_state()),
+            Behaviour::MysteryBlock(block) => BehaviourState::MysteryBlock(block.get_state()),
+            Behaviour::Platform(platform) => BehaviourState::Platform(platform.get_state()),
+            Behaviour::Coin(coin) => BehaviourState::Coin { collected: coin.is_collected() },
+            Behaviour::Star(star) => BehaviourState::Star(star.get_state()),
+            Behaviour::FireFlower(flower) => BehaviourState::FireFlower(flower.get_state()),
+            Behaviour::Fireball(fireball) => BehaviourState::Fireball(fireball.get_state())
+        }
+    }
+
+    /// put back the fields of a state taken from the same kind of entity
+    pub fn set_state(&mut self,state:&BehaviourState) -> GResult<()> {
+        match (self,state) {
+            (Behaviour::Player(player),BehaviourState::Player(state)) => player.set_state(state),
+            (Behaviour::Goomba(goomba),BehaviourState::Goomba(state)) => goomba.set_state(state),
+            (Behaviour::MysteryBlock(block),BehaviourState::MysteryBlock(state)) => block.set_state(state),
+            (Behaviour::Platform(platform),BehaviourState::Platform(state)) => platform.set_state(state),
+            (Behaviour::Coin(coin),BehaviourState::Coin { collected }) => coin.set_collected(*collected),
+            (Behaviour::Star(star),BehaviourState::Star(state)) => star.set_state(state),
+            (Behaviour::FireFlower(flower),BehaviourState::FireFlower(state)) => flower.set_state(state),
+            (Behaviour::Fireball(fireball),BehaviourState::Fireball(state)) => fireball.set_state(state),
+            (_,state) => bail!("the state {:?} is not for this entity",state)
+        }
+
+        Ok(())
+    }
+
+    /// position of the entity when the level started
+    pub fn get_start(&self) -> Vec2 {
+        match self {
+            Behaviour::Player(player) => player.get_start(),
+            Behaviour::Goomba(goomba) => goomba.get_start(),
+            Behaviour::MysteryBlock(block) => block.get_start(),
+            Behaviour::Platform(platform) => platform.get_start(),
+            Behaviour::Coin(coin) => coin.get_start(),
+            Behaviour::Star(star) => star.get_start(),
+            Behaviour::FireFlower(flower) => flower.get_start(),
+            Behaviour::Fireball(fireball) => fireball.get_start()
+        }
+    }
+
+    /// a one-shot clip of the entity sprite reached its last frame
+    pub fn animation_finished(&mut self,id:EntityId,world:&mut World,clip:&str) {
+        match self {
+            Behaviour::Goomba(goomba) => goomba.animation_finished(id,world,clip),
+            Behaviour::Fireball(fireball) => fireball.animation_finished(id,world,clip),
+            _ => {}
+        }
+    }
+
+    pub fn die(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
+        match self {
+            Behaviour::Player(player) => player.die(id,world,physics),
+            Behaviour::Goomba(goomba) => goomba.die(id,world),
+            Behaviour::MysteryBlock(_) | Behaviour::Platform(_) | Behaviour::Coin(_) | Behaviour::Star(_)
+                | Behaviour::FireFlower(_) | Behaviour::Fireball(_) => {}
+        }
+    }
+
+    /// the item hidden in a block start to come out of it
+    pub fn release(&mut self,id:EntityId,world:&mut World) {
+        match self {
+            Behaviour::Star(star) => star.release(id,world),
+            Behaviour::FireFlower(flower) => flower.release(id,world),
+            _ => {}
+        }
+    }
+
+    /// the fireball hit something, it stop and explode
+    pub fn explode(&mut self,id:EntityId,world:&mut World) {
+        if let Behaviour::Fireball(fireball) = self {
+            fireball.explode(id,world);
+        }
+    }
+
+    /// the star knocked off the entity, an enemy fall off the screen
+    pub fn knock_off(&mut self,id:EntityId,world:&mut World,physics:&PhysicsProfile) {
+        if let Behaviour::Goomba(goomba) = self {
+            goomba.knock_off(id,world,physics);
+        }
+    }
+
+    /// the entity got hit from below, return true when it gave a coin
+    pub fn bump(&mut self,id:EntityId,world:&mut World) -> bool {
+        match self {
+            Behaviour::MysteryBlock(block) => block.collect(id,world),
+            _ => false
+        }
+    }
+
+    /// the player touched the entity, return what he picked up if he didn't have it yet
+    pub fn touch(&mut self,id:EntityId,world:&mut World) -> Option<Pickup> {
+        match self {
+            Behaviour::Coin(coin) if !coin.is_collected() => {
+                coin.collect(id,world);
+                Some(Pickup::Coin)
+            },
+            Behaviour::Star(star) if !star.is_collected() => {
+                star.collect(id,world);
+                Some(Pickup::Star)
+            },
+            Behaviour::FireFlower(flower) if !flower.is_collected() => {
+                flower.collect(id,world);
+                Some(Pickup::FireFlower)
+            },
+            _ => None
+        }
+    }
+
+    /// the player can pick the entity up by touching it
+    pub fn is_collectible(&self) -> bool {
+        match self {
+            Behaviour::Coin(coin) => !coin.is_collected(),
+            Behaviour::Star(star) => !star.is_collected(),
+            Behaviour::FireFlower(flower) => !flower.is_collected(),
+            _ => false
+        }
+    }
+
+    /// make a walking entity go in the given direction
+    pub fn turn_around(&mut self,direction:Direction) {
+        if let Behaviour::Goomba(goomba) = self {
+            goomba.turn_around(direction);
+        }
+    }
+
+    /// remove the entity from the level without playing any animation
+    pub fn despawn(&mut self,id:EntityId,world:&mut World) {
+        match self {
+            Behaviour::Goomba(goomba) => goomba.despawn(id,world),
+            Behaviour::Fireball(fireball) => fireball.despawn(id,world),
+            _ => {}
+        }
+    }
+
+    /// a goomba waiting for the player start walking right away
+    pub fn unfreeze(&mut self) {
+        if let Behaviour::Goomba(goomba) = self {
+            goomba.unfreeze();
+        }
+    }
+
+    /// the entity stay still until the camera get close to it, like the enemies of the original game
+    pub fn spawn_on_approach(&self) -> bool {
+        matches!(self,Behaviour::Goomba(_))
+    }
+
+    /// the entity was removed from the level and wait for a reset to come back
+    pub fn is_gone(&self) -> bool {
+        matches!(self,Behaviour::Goomba(goomba) if goomba.is_gone())
+    }
+
+    /// the entity is removed as soon as it leave the view, like the fireballs
+    pub fn despawn_off_view(&self) -> bool {
+        matches!(self,Behaviour::Fireball(_))
+    }
+
+    /// the entity kill the enemies it touch
+    pub fn is_projectile(&self) -> bool {
+        matches!(self,Behaviour::Fireball(fireball) if !fireball.is_exploding())
+    }
+
+    /// the entity kill the player when it touch him
+    pub fn is_enemy(&self) -> bool {
+        match self {
+            Behaviour::Goomba(goomba) => !goomba.is_dying(),
+            _ => false
+        }
+    }
+
+    pub fn as_player(&self) -> Option<&Player> {
+        match self {
+            Behaviour::Player(player) => Some(player),
+            _ => None
+        }
+    }
+
+    pub fn as_player_mut(&mut self) -> Option<&mut Player> {
+        match self {
+            Behaviour::Player(player) => Some(player),
+            _ => None
+        }
+    }
+
+}
+
+
+/// every object of the level, each entity is an index in the component lists
+pub struct World {
+    pub positions:      Vec<Option<Vec2>>,
+    pub velocities:     Vec<Option<Vec2>>,
+    pub colliders:      Vec<Option<Collider>>,
+    pub sprites:        Vec<Option<Sprite>>,
+    pub behaviours:     Vec<Option<Behaviour>>,
+    /// solids touched during the last movement
+    pub contacts:       Vec<Vec<Contact>>,
+    /// position before the last movement
+    pub previous:       Vec<Vec2>,
+    /// only the active entities are updated and drawn
+    pub active:         Vec<bool>,
+    player:             Option<EntityId>,
+    /// sound asked by the entities since the last frame
+    sounds:             Vec<SoundEffect>
+}
+
+impl World {
+
+    pub fn new() -> Self {
+        Self {
+            positions: Vec::new(),
+            velocities: Vec::new(),
+            colliders: Vec::new(),
+            sprites: Vec::new(),
+            behaviours: Vec::new(),
+            contacts: Vec::new(),
+            previous: Vec::new(),
+            active: Vec::new(),
+            player: None,
+            sounds: Vec::new()
+        }
+    }
+
+    /// create an entity without any component
+    pub fn spawn(&mut self) -> EntityId {
+
+        self.positions.push(None);
+        self.velocities.push(None);
+        self.colliders.push(None);
+        self.sprites.push(None);
+        self.behaviours.push(None);
+        self.contacts.push(Vec::new());
+        self.previous.push(Vec2::new(0.0,0.0));
+        self.active.push(true);
+
+        self.positions.len() - 1
+
+    }
+
+    pub fn entities(&self) -> Range<EntityId> { 0..self.positions.len() }
+
+    /// entities that are updated and drawn this frame
+    pub fn active_entities(&self) -> Vec<EntityId> {
+        self.entities().filter(|id| self.active[*id]).collect()
+    }
+
+    pub fn set_player(&mut self,id:EntityId) { self.player = Some(id); }
+    pub fn get_player(&self) -> Option<EntityId> { self.player }
+
+    pub fn get_position(&self,id:EntityId) -> Option<&Vec2> { self.positions[id].as_ref() }
+    pub fn get_velocity(&self,id:EntityId) -> Option<&Vec2> { self.velocities[id].as_ref() }
+    pub fn get_velocity_mut(&mut self,id:EntityId) -> Option<&mut Vec2> { self.velocities[id].as_mut() }
+    pub fn get_sprite_mut(&mut self,id:EntityId) -> Option<&mut Sprite> { self.sprites[id].as_mut() }
+    pub fn get_behaviour(&self,id:EntityId) -> Option<&Behaviour> { self.behaviours[id].as_ref() }
+    pub fn get_behaviour_mut(&mut self,id:EntityId) -> Option<&mut Behaviour> { self.behaviours[id].as_mut() }
+    pub fn get_contacts(&self,id:EntityId) -> &[Contact] { &self.contacts[id] }
+    pub fn get_previous(&self,id:EntityId) -> &Vec2 { &self.previous[id] }
+    pub fn is_active(&self,id:EntityId) -> bool { self.active[id] }
+
+    /// ask for a sound, the audio manager play it at the end of the frame
+    pub fn play_sound(&mut self,effect:SoundEffect) { self.sounds.push(effect); }
+    pub fn take_sounds(&mut self) -> Vec<SoundEffect> { std::mem::take(&mut self.sounds) }
+
+    /// hitbox of the entity at its current position
+    pub fn get_rect(&self,id:EntityId) -> Option<Rect> {
+        match (self.positions[id], self.colliders[id]) {
+            (Some(pos), Some(collider)) => Some(Rect::new(pos.x,pos.y,collider.width,collider.height)),
+            _ => None
+        }
+    }
+
+    /// give the behaviour of an entity a mutable access to the rest of the world
+    pub fn with_behaviour<F>(&mut self,id:EntityId,f:F) where F: FnOnce(&mut Behaviour,&mut World) {
+
+        if let Some(mut behaviour) = self.behaviours[id].take() {
+            f(&mut behaviour,self);
+            self.behaviours[id] = Some(behaviour);
+        }
+
+    }
+
+}